@@ -0,0 +1,144 @@
+//! Credentials and extra headers sent with every request, kept out of logs.
+
+use std::{fmt, str::FromStr};
+
+use base64::Engine;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+
+use crate::{Error, Result};
+
+// ------ Secrets ------
+
+/// A credential that prints as `***` in `Debug` and `Display`, so it cannot leak into logs.
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Wraps a credential.
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The credential itself, for building the request header.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl FromStr for Secret {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+// ------ Credentials ------
+
+/// `USER:PASSWORD` for HTTP basic auth.
+#[derive(Clone, Debug)]
+pub struct BasicAuth {
+    /// The user name, sent as is.
+    pub username: String,
+    /// The password.
+    pub password: Secret,
+}
+
+impl FromStr for BasicAuth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (username, password) = s
+            .split_once(':')
+            .ok_or_else(|| "expected USER:PASSWORD".to_string())?;
+        Ok(Self {
+            username: username.to_string(),
+            password: Secret::new(password),
+        })
+    }
+}
+
+/// An extra request header given as `Name: value`. The value is treated as secret.
+#[derive(Clone, Debug)]
+pub struct HeaderArg {
+    /// The header name, e.g. `X-Team`.
+    pub name: String,
+    /// The header value.
+    pub value: Secret,
+}
+
+impl FromStr for HeaderArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected `Name: value`, got `{}`", s))?;
+        Ok(Self {
+            name: name.trim().to_string(),
+            value: Secret::new(value.trim()),
+        })
+    }
+}
+
+/// Everything the transport attaches to requests to authenticate them.
+#[derive(Clone, Debug, Default)]
+pub struct Auth {
+    /// API key sent as `Authorization: Bearer`; preferred over `basic`.
+    pub bearer: Option<Secret>,
+    /// Credentials sent as `Authorization: Basic`.
+    pub basic: Option<BasicAuth>,
+    /// Extra headers; later ones replace earlier ones of the same name.
+    pub headers: Vec<HeaderArg>,
+}
+
+impl Auth {
+    /// Builds the default headers for every request. All values are marked
+    /// sensitive so reqwest's own debug output redacts them too.
+    pub fn header_map(&self) -> Result<HeaderMap> {
+        let mut map = HeaderMap::new();
+        let authorization = match (&self.bearer, &self.basic) {
+            (Some(key), _) => Some(format!("Bearer {}", key.expose())),
+            (None, Some(basic)) => Some(format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(format!(
+                    "{}:{}",
+                    basic.username,
+                    basic.password.expose()
+                ))
+            )),
+            (None, None) => None,
+        };
+        if let Some(authorization) = authorization {
+            let mut value = HeaderValue::from_str(&authorization).map_err(|_| {
+                Error::config("API key contains characters not allowed in a header")
+            })?;
+            value.set_sensitive(true);
+            map.insert(AUTHORIZATION, value);
+        }
+        for header in &self.headers {
+            let name = HeaderName::from_bytes(header.name.as_bytes())
+                .map_err(|_| Error::config(format!("invalid header name `{}`", header.name)))?;
+            let mut value = HeaderValue::from_str(header.value.expose()).map_err(|_| {
+                Error::config(format!("invalid value for header `{}`", header.name))
+            })?;
+            value.set_sensitive(true);
+            map.insert(name, value);
+        }
+        Ok(map)
+    }
+}
@@ -0,0 +1,788 @@
+//! Command-line interface of the `simple-llm-query` binary.
+
+use std::{
+    fs,
+    io::{IsTerminal, Write as _},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{Args, Parser, Subcommand};
+use reqwest::Url;
+use tokio::io::{self, AsyncReadExt, AsyncWrite};
+use tracing::{error, info};
+
+#[cfg(feature = "mock")]
+use crate::mock_server::{self, MockServer, Script};
+use crate::{
+    ChatEvents, ChatMessage, ChatRequest, ChatResponse, ContentPart, DataUrlEncoder, Error,
+    HttpTransport, ImageEncoder, LlmClient, LlmTransport, Result, Role, SamplingParams,
+    auth::{Auth, BasicAuth, HeaderArg, Secret},
+    build_request_content,
+    config::{Config, Profile},
+    images::{OutputFormat, ResizingEncoder},
+    output::{self, EventWriter, OutputOptions, print_events},
+    prompt::{self, Prompt},
+    repl,
+    session::{self, Session, SessionStore},
+    stats::{Stats, StatusLine},
+    structured::StructuredOutput,
+    tools::{self, CommandTool, ToolRegistry},
+};
+#[cfg(feature = "mock")]
+use std::net::{IpAddr, SocketAddr};
+
+// ------ CLI and Configuration ------
+
+/// The command line, parsed with [`clap::Parser`].
+#[derive(Parser, Debug)]
+#[command(author, version, about = None, long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The prompt to send to the LLM (`-` reads it from stdin); piped stdin is attached to it
+    #[arg(short, long)]
+    prompt: Option<String>,
+
+    /// Read the prompt from a file instead of --prompt
+    #[arg(long, value_name = "FILE", conflicts_with = "prompt")]
+    prompt_file: Option<PathBuf>,
+
+    /// Never read stdin, even when it is not a terminal
+    #[arg(long)]
+    no_stdin: bool,
+
+    /// Fill a `{{NAME}}` placeholder in the prompt (repeatable); `{{stdin}}` inlines piped input
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = prompt::parse_var)]
+    vars: Vec<(String, String)>,
+
+    /// Image to attach: a file path, an http(s) URL or a data URL (repeatable, sent in order)
+    #[arg(short, long = "image", value_name = "IMAGE")]
+    images: Vec<String>,
+
+    /// Shrink local images so their longest edge is at most this many pixels
+    #[arg(long, global = true, value_name = "PX")]
+    image_max_size: Option<u32>,
+
+    /// JPEG quality (1-100) used when re-encoding local images
+    #[arg(
+        long,
+        global = true,
+        value_name = "1-100",
+        value_parser = clap::value_parser!(u8).range(1..=100)
+    )]
+    image_quality: Option<u8>,
+
+    /// Format used when re-encoding local images
+    #[arg(long, global = true, value_enum)]
+    image_format: Option<OutputFormat>,
+
+    /// Optional system prompt sent before the conversation
+    #[arg(short, long, global = true, env = "SIMPLE_LLM_QUERY_SYSTEM")]
+    system: Option<String>,
+
+    /// Prior conversation turns as ROLE=TEXT (repeatable, sent in order)
+    #[arg(long = "message", value_name = "ROLE=TEXT")]
+    messages: Vec<Turn>,
+
+    /// Whether to perform a review step after the initial response
+    #[arg(long, overrides_with = "no_review")]
+    review: bool,
+
+    /// Skip the review step even if the profile enables it
+    #[arg(long, overrides_with = "review")]
+    no_review: bool,
+
+    /// Named session to continue; the conversation is saved back after each reply
+    #[arg(long, global = true)]
+    session: Option<String>,
+
+    /// Ask for the whole response at once instead of streaming it
+    #[arg(long, global = true)]
+    no_stream: bool,
+
+    /// Ask for a JSON answer matching this JSON Schema file and validate it
+    #[arg(long, global = true, value_name = "FILE")]
+    json_schema: Option<PathBuf>,
+
+    /// Ask the server to enforce the schema strictly; OpenAI then only accepts
+    /// schemas with `additionalProperties: false` and all properties required
+    #[arg(long, global = true, requires = "json_schema")]
+    strict_schema: bool,
+
+    /// Ask for a JSON object answer (no schema) and validate that it parses
+    #[arg(long, global = true, conflicts_with = "json_schema")]
+    json: bool,
+
+    /// Print structured JSON answers on one line instead of pretty-printed
+    #[arg(long, global = true)]
+    compact: bool,
+
+    /// Print token counts, time to first token and tokens per second to stderr
+    #[arg(long, global = true)]
+    stats: bool,
+
+    /// How to write responses to stdout
+    #[arg(short, long, global = true, value_enum, default_value_t)]
+    output: output::Format,
+
+    /// Print the answer as it is instead of rendering Markdown on a terminal
+    #[arg(long, global = true)]
+    raw: bool,
+
+    /// Let the model call a built-in tool (repeatable): read_file, list_directory
+    #[arg(
+        long = "tool",
+        global = true,
+        value_name = "NAME",
+        value_parser = clap::builder::PossibleValuesParser::new(tools::BUILTIN_TOOLS)
+    )]
+    tools: Vec<String>,
+
+    /// Directory the built-in tools may read; paths outside it are refused [default: the current directory]
+    #[arg(long, global = true, value_name = "DIR")]
+    tool_root: Option<PathBuf>,
+
+    /// JSON file declaring external command tools the model may call
+    #[arg(long, global = true, value_name = "FILE")]
+    tools_file: Option<PathBuf>,
+
+    /// Model to request, for servers that host more than one
+    #[arg(short, long, global = true, env = "SIMPLE_LLM_QUERY_MODEL")]
+    model: Option<String>,
+
+    /// LLM endpoint [default: http://localhost:8080/v1/chat/completions]
+    #[arg(long, global = true, env = "SIMPLE_LLM_QUERY_ENDPOINT")]
+    llm_endpoint: Option<String>,
+
+    /// API key sent as a bearer token
+    #[arg(
+        long,
+        global = true,
+        env = "SIMPLE_LLM_QUERY_API_KEY",
+        hide_env_values = true
+    )]
+    api_key: Option<Secret>,
+
+    /// Credentials for HTTP basic auth
+    #[arg(
+        long,
+        global = true,
+        value_name = "USER:PASSWORD",
+        hide_env_values = true
+    )]
+    basic_auth: Option<BasicAuth>,
+
+    /// Extra request header (repeatable)
+    #[arg(long = "header", global = true, value_name = "NAME: VALUE")]
+    headers: Vec<HeaderArg>,
+
+    /// Config file [default: config.toml in the user config directory]
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        env = "SIMPLE_LLM_QUERY_CONFIG"
+    )]
+    config: Option<PathBuf>,
+
+    /// Named profile from the config file
+    #[arg(long, global = true, env = "SIMPLE_LLM_QUERY_PROFILE")]
+    profile: Option<String>,
+
+    #[command(flatten)]
+    sampling: SamplingArgs,
+}
+
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Sampling")]
+struct SamplingArgs {
+    /// Sampling temperature; lower is more deterministic
+    #[arg(long, global = true)]
+    temperature: Option<f32>,
+
+    /// Nucleus sampling: keep the smallest token set with this cumulative probability
+    #[arg(long, global = true)]
+    top_p: Option<f32>,
+
+    /// Only sample from the K most likely tokens
+    #[arg(long, global = true)]
+    top_k: Option<u32>,
+
+    /// Drop tokens less likely than this fraction of the most likely token
+    #[arg(long, global = true)]
+    min_p: Option<f32>,
+
+    /// Maximum number of tokens to generate
+    #[arg(long, global = true)]
+    max_tokens: Option<u32>,
+
+    /// Stop generating at this sequence (repeatable)
+    #[arg(long, global = true)]
+    stop: Vec<String>,
+
+    /// Random seed for reproducible sampling (-1 lets llama.cpp pick one)
+    #[arg(long, global = true, allow_negative_numbers = true)]
+    seed: Option<i64>,
+
+    /// Penalty applied to recently repeated tokens
+    #[arg(long, global = true)]
+    repeat_penalty: Option<f32>,
+
+    /// Penalty for tokens that already appeared at all
+    #[arg(long, global = true, allow_hyphen_values = true)]
+    presence_penalty: Option<f32>,
+
+    /// Penalty proportional to how often a token already appeared
+    #[arg(long, global = true, allow_hyphen_values = true)]
+    frequency_penalty: Option<f32>,
+}
+
+impl SamplingArgs {
+    /// Copies every parameter that was given onto the request.
+    fn apply(&self, mut req: ChatRequest) -> ChatRequest {
+        if !self.stop.is_empty() {
+            req.sampling.stop.clear();
+        }
+        if let Some(v) = self.temperature {
+            req = req.temperature(v);
+        }
+        if let Some(v) = self.top_p {
+            req = req.top_p(v);
+        }
+        if let Some(v) = self.top_k {
+            req = req.top_k(v);
+        }
+        if let Some(v) = self.min_p {
+            req = req.min_p(v);
+        }
+        if let Some(v) = self.max_tokens {
+            req = req.max_tokens(v);
+        }
+        for s in &self.stop {
+            req = req.stop(s.as_str());
+        }
+        if let Some(v) = self.seed {
+            req = req.seed(v);
+        }
+        if let Some(v) = self.repeat_penalty {
+            req = req.repeat_penalty(v);
+        }
+        if let Some(v) = self.presence_penalty {
+            req = req.presence_penalty(v);
+        }
+        if let Some(v) = self.frequency_penalty {
+            req = req.frequency_penalty(v);
+        }
+        req
+    }
+}
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:8080/v1/chat/completions";
+const DEFAULT_REVIEW_PROMPT: &str = "Please review and revise.";
+
+/// Effective settings after layering flags, environment, profile and defaults.
+struct Settings {
+    endpoint: Url,
+    model: Option<String>,
+    auth: Auth,
+    system: Option<String>,
+    review: bool,
+    review_prompt: String,
+    sampling: SamplingParams,
+}
+
+impl Settings {
+    /// Reads the config file and resolves the selected profile against the flags.
+    fn load(cli: &Cli) -> Result<Self> {
+        let profile = Config::load(cli.config.as_deref())?.profile(cli.profile.as_deref())?;
+        Self::resolve(cli, profile)
+    }
+
+    /// Flags win over environment variables (clap merges those two), which win
+    /// over the profile, which wins over the built-in defaults.
+    fn resolve(cli: &Cli, profile: Profile) -> Result<Self> {
+        let endpoint = cli
+            .llm_endpoint
+            .clone()
+            .or(profile.endpoint)
+            .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+        // Credentials form one layer: `--api-key` or `--basic-auth` replaces
+        // all of the profile's credentials, so it never reads their variables.
+        let (bearer, basic) = if cli.api_key.is_some() || cli.basic_auth.is_some() {
+            (cli.api_key.clone(), cli.basic_auth.clone())
+        } else {
+            let bearer = match &profile.api_key_env {
+                Some(var) => Some(Secret::new(read_env(var, "API key")?)),
+                None => None,
+            };
+            let basic = match &profile.basic_auth {
+                Some(basic) => Some(BasicAuth {
+                    username: basic.username.clone(),
+                    password: Secret::new(read_env(&basic.password_env, "basic auth password")?),
+                }),
+                None => None,
+            };
+            (bearer, basic)
+        };
+        // Flag headers come last so they replace profile headers of the same name.
+        let headers = profile
+            .headers
+            .iter()
+            .map(|(name, value)| HeaderArg {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .chain(cli.headers.iter().cloned())
+            .collect();
+        let review = if cli.review {
+            true
+        } else if cli.no_review {
+            false
+        } else {
+            profile.review.unwrap_or(false)
+        };
+        Ok(Self {
+            endpoint: Url::parse(&endpoint)
+                .map_err(|e| Error::config(format!("invalid endpoint `{}`: {}", endpoint, e)))?,
+            model: cli.model.clone().or(profile.model),
+            auth: Auth {
+                bearer,
+                basic,
+                headers,
+            },
+            system: cli.system.clone().or(profile.system),
+            review,
+            review_prompt: profile
+                .review_prompt
+                .unwrap_or_else(|| DEFAULT_REVIEW_PROMPT.to_string()),
+            sampling: profile.sampling,
+        })
+    }
+}
+
+fn read_env(var: &str, what: &str) -> Result<String> {
+    std::env::var(var).map_err(|_| {
+        Error::config(format!(
+            "the profile reads its {} from ${}, which is not set",
+            what, var
+        ))
+    })
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Start an interactive chat that keeps the conversation history
+    Chat,
+    /// List the models the server offers
+    Models,
+    /// Manage saved chat sessions
+    Sessions {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+    /// Serve a fake OpenAI-compatible API locally, answering from a script
+    #[cfg(feature = "mock")]
+    MockServer {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: IpAddr,
+        /// Port to listen on (0 picks a free one)
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        /// TOML script with rules, canned replies and injected errors; without one, prompts are echoed
+        #[arg(long, value_name = "FILE")]
+        script: Option<PathBuf>,
+        /// Pause before each streamed token, overriding the script
+        #[arg(long, value_name = "MS")]
+        token_delay_ms: Option<u64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SessionAction {
+    /// List saved sessions
+    List,
+    /// Print the messages of a session
+    Show { name: String },
+    /// Delete a session
+    Delete { name: String },
+}
+
+/// A prior conversation turn given on the command line as `ROLE=TEXT`.
+#[derive(Clone, Debug)]
+struct Turn {
+    role: Role,
+    text: String,
+}
+
+impl FromStr for Turn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (role, text) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected ROLE=TEXT, got `{}`", s))?;
+        Ok(Self {
+            role: role.parse()?,
+            text: text.to_string(),
+        })
+    }
+}
+
+impl From<&Turn> for ChatMessage {
+    fn from(turn: &Turn) -> Self {
+        Self::new(turn.role, vec![ContentPart::text(turn.text.as_str())])
+    }
+}
+
+// ------ Commands ------
+
+/// Runs the command line against the configured HTTP server.
+pub async fn run(cli: Cli) -> Result<()> {
+    #[cfg(feature = "mock")]
+    if let Some(Command::MockServer {
+        host,
+        port,
+        script,
+        token_delay_ms,
+    }) = &cli.command
+    {
+        return run_mock_server(
+            SocketAddr::new(*host, *port),
+            script.as_deref(),
+            *token_delay_ms,
+        )
+        .await;
+    }
+    // Sessions are local, so a profile's credentials must not be required for them.
+    if let Some(Command::Sessions { action }) = &cli.command {
+        return run_sessions(action);
+    }
+    let settings = Settings::load(&cli)?;
+    let transport = HttpTransport::with_auth(&settings.auth)?;
+    execute(&cli, &settings, transport).await
+}
+
+/// Like [`run`], but sends every request through `transport`, e.g. a
+/// `mock::MockTransport` in tests. Credentials and
+/// headers are ignored, since they are the transport's business.
+pub async fn run_with_transport<T: LlmTransport>(cli: Cli, transport: T) -> Result<()> {
+    if let Some(Command::Sessions { action }) = &cli.command {
+        return run_sessions(action);
+    }
+    let settings = Settings::load(&cli)?;
+    execute(&cli, &settings, transport).await
+}
+
+async fn execute<T: LlmTransport>(cli: &Cli, settings: &Settings, transport: T) -> Result<()> {
+    let mut tools = ToolRegistry::new();
+    if !cli.tools.is_empty() {
+        let root = cli.tool_root.as_deref().unwrap_or(Path::new("."));
+        let root = fs::canonicalize(root)
+            .map_err(|e| Error::config(format!("invalid tool root {}: {}", root.display(), e)))?;
+        for name in &cli.tools {
+            let tool = tools::builtin(name, &root)
+                .ok_or_else(|| Error::config(format!("unknown tool `{}`", name)))?;
+            tools.register(tool);
+        }
+    }
+    if let Some(path) = &cli.tools_file {
+        for tool in CommandTool::load_file(path)? {
+            tools.register(Box::new(tool));
+        }
+    }
+    let client = LlmClient::new(transport, settings.endpoint.clone()).with_tools(tools);
+    // Re-encoding is opt-in: without any image flags files only lose their metadata.
+    let encoder: Box<dyn ImageEncoder> = if cli.image_max_size.is_some()
+        || cli.image_quality.is_some()
+        || cli.image_format.is_some()
+    {
+        Box::new(ResizingEncoder::new(
+            cli.image_max_size,
+            cli.image_quality.unwrap_or(85),
+            cli.image_format.unwrap_or_default(),
+        ))
+    } else {
+        Box::new(DataUrlEncoder)
+    };
+    let mut template = cli.sampling.apply(
+        ChatRequest::new()
+            .stream(!cli.no_stream)
+            .with_sampling(settings.sampling.clone()),
+    );
+    if let Some(model) = &settings.model {
+        template = template.model(model);
+    }
+    let structured = match &cli.json_schema {
+        Some(path) => {
+            Some(StructuredOutput::from_schema_file(path, cli.compact)?.strict(cli.strict_schema))
+        }
+        None if cli.json => Some(StructuredOutput::json(cli.compact)),
+        None => None,
+    };
+    if let Some(structured) = &structured {
+        template = template.response_format(structured.response_format());
+    }
+    let output = OutputOptions {
+        format: cli.output,
+        stats: cli.stats,
+        render_markdown: !cli.raw && std::io::stdout().is_terminal(),
+    };
+
+    match &cli.command {
+        Some(Command::Chat) => {
+            let session = cli.session.as_deref().map(Session::open).transpose()?;
+            let (stored_system, mut history) = match &session {
+                Some(session) => session::split_system(session.load()?),
+                None => (None, vec![]),
+            };
+            history.extend(cli.messages.iter().map(ChatMessage::from));
+            let system = settings.system.clone().or(stored_system);
+            repl::Repl::new(
+                &client,
+                encoder.as_ref(),
+                template,
+                system,
+                history,
+                session,
+                output,
+            )
+            .run()
+            .await
+        }
+        Some(Command::Models) => run_models(&client).await,
+        Some(Command::Sessions { action }) => run_sessions(action),
+        #[cfg(feature = "mock")]
+        Some(Command::MockServer { .. }) => Err(Error::config(
+            "mock-server cannot run with a custom transport",
+        )),
+        None => {
+            let prompt = run_prompt(
+                cli,
+                settings,
+                &client,
+                encoder.as_ref(),
+                template,
+                structured.as_ref(),
+                output,
+            );
+            // Also listens between the answer and its review; the prompt goes
+            // first so an answer in progress can clear its status line.
+            tokio::select! {
+                biased;
+                result = prompt => result,
+                _ = tokio::signal::ctrl_c() => Err(Error::Cancelled),
+            }
+        }
+    }
+}
+
+/// Gathers the prompt from --prompt, --prompt-file and piped stdin.
+async fn read_prompt(cli: &Cli) -> Result<Prompt> {
+    let from_stdin = cli.prompt.as_deref() == Some("-");
+    let piped = !cli.no_stdin && !std::io::stdin().is_terminal();
+    let stdin = if from_stdin || piped {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).await?;
+        Some(text)
+    } else {
+        None
+    };
+    let instruction = match (&cli.prompt, &cli.prompt_file) {
+        _ if from_stdin => None,
+        (Some(prompt), _) => Some(prompt.clone()),
+        (None, Some(path)) => Some(Prompt::read_file(path)?),
+        (None, None) => None,
+    };
+    Prompt::resolve(instruction, &cli.vars, stdin)
+}
+
+/// Sends a request and prints the answer in the chosen format. In structured
+/// mode, text output waits until the answer has been validated.
+pub(crate) async fn ask<T: LlmTransport>(
+    client: &LlmClient<T>,
+    request: ChatRequest,
+    structured: Option<&StructuredOutput>,
+    output: OutputOptions,
+) -> Result<ChatResponse> {
+    let events = client.chat_stream(request);
+    match (structured, output.format) {
+        (Some(_), output::Format::Text | output::Format::Markdown) => {
+            let writer = EventWriter::new(output::Format::Text, io::sink());
+            respond(events, writer, structured, output).await
+        }
+        _ => {
+            let writer = EventWriter::new(output.format, io::stdout())
+                .render_markdown(output.render_markdown);
+            respond(events, writer, structured, output).await
+        }
+    }
+}
+
+async fn respond<W: AsyncWrite + Unpin>(
+    events: ChatEvents<'_>,
+    mut writer: EventWriter<W>,
+    structured: Option<&StructuredOutput>,
+    output: OutputOptions,
+) -> Result<ChatResponse> {
+    let mut status = StatusLine::new(output.stats);
+    let response = print_events(events, &mut writer, &mut status).await?;
+    response.log_stats();
+    if output.stats {
+        eprintln!("{}", Stats::from(&response));
+    }
+    if let Some(structured) = structured {
+        let value = structured.parse(&response.content)?;
+        if writer.format() == output::Format::Text {
+            writeln!(std::io::stdout().lock(), "{}", structured.render(&value))?;
+        }
+    }
+    writer.finish(&response).await?;
+    Ok(response)
+}
+
+async fn run_models<T: LlmTransport>(client: &LlmClient<T>) -> Result<()> {
+    let models = client.models().await?;
+    let width = models.iter().map(|m| m.id.len()).max().unwrap_or(0).max(2);
+    // Written rather than printed, so a closed pipe is an error instead of a panic.
+    let mut out = std::io::stdout().lock();
+    writeln!(out, "{:<width$}  {:<12}  CONTEXT", "ID", "OWNER")?;
+    for model in models {
+        writeln!(
+            out,
+            "{:<width$}  {:<12}  {}",
+            model.id,
+            model.owned_by.as_deref().unwrap_or("-"),
+            model
+                .context_length
+                .map_or_else(|| "-".to_string(), |n| n.to_string()),
+        )?;
+    }
+    Ok(())
+}
+
+fn run_sessions(action: &SessionAction) -> Result<()> {
+    let store = SessionStore::open_default()?;
+    let mut out = std::io::stdout().lock();
+    match action {
+        SessionAction::List => {
+            for summary in store.list()? {
+                writeln!(out, "{}\t{} messages", summary.name, summary.messages)?;
+            }
+        }
+        SessionAction::Show { name } => {
+            let messages = store.load(name)?;
+            if messages.is_empty() {
+                return Err(Error::config(format!("session `{}` does not exist", name)));
+            }
+            session::write_messages(&mut out, &messages)?;
+        }
+        SessionAction::Delete { name } => {
+            store.delete(name)?;
+            writeln!(out, "Deleted session {}.", name)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "mock")]
+async fn run_mock_server(
+    addr: SocketAddr,
+    script: Option<&Path>,
+    token_delay_ms: Option<u64>,
+) -> Result<()> {
+    let mut script = match script {
+        Some(path) => Script::load(path)?,
+        None => Script::default(),
+    };
+    if let Some(delay) = token_delay_ms {
+        script.token_delay_ms = delay;
+    }
+    let server = MockServer::new(script)?;
+    let listener = mock_server::bind(addr)?;
+    info!(
+        "Mock server listening on http://{}/v1/chat/completions",
+        listener.local_addr()?
+    );
+    mock_server::serve(listener, server).await
+}
+
+async fn run_prompt<T: LlmTransport>(
+    cli: &Cli,
+    settings: &Settings,
+    client: &LlmClient<T>,
+    encoder: &dyn ImageEncoder,
+    template: ChatRequest,
+    structured: Option<&StructuredOutput>,
+    output: OutputOptions,
+) -> Result<()> {
+    let prompt = read_prompt(cli).await?;
+
+    let session = cli.session.as_deref().map(Session::open).transpose()?;
+    let (stored_system, mut history) = match &session {
+        Some(session) => session::split_system(session.load()?),
+        None => (None, vec![]),
+    };
+    if let Some(system) = settings.system.clone().or(stored_system) {
+        history.insert(0, ChatMessage::system(system));
+    }
+    history.extend(cli.messages.iter().map(ChatMessage::from));
+
+    info!("Building initial request content...");
+    let parts = build_request_content(&prompt, &cli.images, encoder)?;
+    let user_message = ChatMessage::user(parts);
+    let initial_req = template
+        .clone()
+        .with_messages(history.clone())
+        .message(user_message.clone());
+
+    let first = if settings.review && output.format == output::Format::Json {
+        // `json` prints one object, so only the reviewed answer is written.
+        let writer = EventWriter::new(output::Format::Json, io::sink());
+        respond(client.chat_stream(initial_req), writer, structured, output).await
+    } else {
+        ask(client, initial_req, structured, output).await
+    };
+    let mut reply = match first {
+        Ok(res) => res,
+        Err(e) => {
+            error!("LLM request failed: {}", e);
+            return Err(e);
+        }
+    };
+
+    if settings.review {
+        // Separates the two answers for a reader; JSON formats stay machine-readable.
+        if matches!(
+            output.format,
+            output::Format::Text | output::Format::Markdown
+        ) {
+            writeln!(std::io::stdout().lock())?;
+        }
+        info!("Building review request...");
+        let review_prompt = Prompt {
+            instruction: Some(format!(
+                "Original prompt: \"{}\"\n\nFirst response: \"{}\"\n\n{}",
+                prompt.instruction.as_deref().unwrap_or_default(),
+                reply.content,
+                settings.review_prompt
+            )),
+            documents: prompt.documents.clone(),
+        };
+        let review_parts = build_request_content(&review_prompt, &cli.images, encoder)?;
+        let review_req = template
+            .with_messages(history.clone())
+            .message(ChatMessage::user(review_parts));
+        reply = ask(client, review_req, structured, output).await?;
+    }
+
+    if let Some(session) = session {
+        history.push(user_message);
+        history.push(ChatMessage::assistant(reply.content));
+        session.save(&history)?;
+    }
+
+    Ok(())
+}
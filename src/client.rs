@@ -0,0 +1,512 @@
+//! The chat client and the events and results it produces.
+
+use std::{
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use futures::{Stream, StreamExt, pin_mut};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{
+    Error, Result,
+    message::{ChatMessage, FunctionCall, ToolCall, function_kind},
+    models::{ModelInfo, models_url},
+    request::ChatRequest,
+    sse,
+    tools::ToolRegistry,
+    transport::{ApiError, LlmTransport},
+};
+
+// ------ Completion Response ------
+
+/// Body of a non-streaming chat completion.
+#[derive(Deserialize)]
+struct CompletionResponse {
+    choices: Vec<CompletionChoice>,
+    usage: Option<Usage>,
+    timings: Option<Timings>,
+}
+
+#[derive(Deserialize)]
+struct CompletionChoice {
+    message: CompletionMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CompletionMessage {
+    content: Option<String>,
+    /// Chain-of-thought text, as sent by llama.cpp and DeepSeek-style servers.
+    #[serde(alias = "reasoning")]
+    reasoning_content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCall>,
+}
+
+/// Token counts reported by the server.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Usage {
+    /// Tokens in the request.
+    pub prompt_tokens: u64,
+    /// Tokens generated.
+    pub completion_tokens: u64,
+}
+
+/// Server-side timings that llama.cpp adds to its final chunk.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Timings {
+    /// Prompt tokens evaluated (excluding any reused from the cache).
+    pub prompt_n: u64,
+    /// Time spent evaluating the prompt.
+    pub prompt_ms: f64,
+    /// Tokens generated.
+    pub predicted_n: u64,
+    /// Time spent generating.
+    pub predicted_ms: f64,
+}
+
+/// One streamed `tool_calls` fragment. The first fragment for an index carries
+/// the id and function name; later ones only append to the arguments string.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ToolCallDelta {
+    /// Which of the round's tool calls this fragment belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// The call's id, on its first fragment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The function name and a piece of its arguments.
+    #[serde(default)]
+    pub function: FunctionDelta,
+}
+
+/// The function part of a [`ToolCallDelta`].
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FunctionDelta {
+    /// The function name, on the call's first fragment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// More of the JSON-encoded arguments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+}
+
+impl From<(usize, &ToolCall)> for ToolCallDelta {
+    fn from((index, call): (usize, &ToolCall)) -> Self {
+        Self {
+            index: Some(index),
+            id: Some(call.id.clone()),
+            function: FunctionDelta {
+                name: Some(call.function.name.clone()),
+                arguments: Some(call.function.arguments.clone()),
+            },
+        }
+    }
+}
+
+impl ToolCallDelta {
+    /// Folds this fragment into the calls collected so far.
+    pub fn merge_into(&self, calls: &mut Vec<ToolCall>) {
+        let index = self.index.unwrap_or(calls.len());
+        while calls.len() <= index {
+            calls.push(ToolCall {
+                id: String::new(),
+                kind: function_kind(),
+                function: FunctionCall::default(),
+            });
+        }
+        let call = &mut calls[index];
+        if let Some(id) = &self.id {
+            call.id = id.clone();
+        }
+        if let Some(name) = &self.function.name {
+            call.function.name.push_str(name);
+        }
+        if let Some(arguments) = &self.function.arguments {
+            call.function.arguments.push_str(arguments);
+        }
+    }
+}
+
+// ------ Chat Events ------
+
+/// One step of a chat completion, in the order the server produced it.
+///
+/// Non-streaming responses are reported with the same events, each carrying
+/// the complete value at once. Serializes as `{"type": "content_delta", "data": ...}`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ChatEvent {
+    /// More answer text.
+    ContentDelta(String),
+    /// More chain-of-thought text, for models that expose it separately.
+    ReasoningDelta(String),
+    /// A fragment of a tool call the model is making.
+    ToolCallDelta(ToolCallDelta),
+    /// A tool ran locally; its output goes back to the model in the next round.
+    ToolResult {
+        /// The id of the call this answers.
+        id: String,
+        /// The tool that ran.
+        name: String,
+        /// What it returned, or the error it failed with.
+        output: String,
+    },
+    /// Token counts reported by the server.
+    Usage(Usage),
+    /// Prompt and generation timings reported by llama.cpp.
+    Timings(Timings),
+    /// Why the server stopped generating, e.g. `stop`, `length` or `tool_calls`.
+    FinishReason(String),
+    /// The model gave its final answer; nothing follows.
+    Done,
+}
+
+/// The events of one chat, as returned by [`LlmClient::chat_stream`].
+pub type ChatEvents<'a> = Pin<Box<dyn Stream<Item = Result<ChatEvent>> + 'a>>;
+
+/// Everything a finished chat produced, gathered from its events.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ChatResponse {
+    /// The final answer. Text from rounds that ended in tool calls is left out.
+    pub content: String,
+    /// Chain-of-thought text, for models that expose it separately.
+    pub reasoning: String,
+    /// Why the server stopped generating the final answer.
+    pub finish_reason: Option<String>,
+    /// Token counts summed over all tool rounds, if the server reported them.
+    pub usage: Option<Usage>,
+    /// llama.cpp timings of the last round.
+    pub timings: Option<Timings>,
+    /// From sending the request to the first content or reasoning token.
+    #[serde(
+        rename = "time_to_first_token_ms",
+        serialize_with = "serialize_millis_opt"
+    )]
+    pub time_to_first_token: Option<Duration>,
+    /// From sending the request to the end of the answer.
+    #[serde(rename = "latency_ms", serialize_with = "serialize_millis")]
+    pub latency: Duration,
+    /// Content and reasoning deltas received; about one per token when streaming.
+    #[serde(skip)]
+    pub(crate) deltas: u64,
+}
+
+fn serialize_millis<S: serde::Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64() * 1000.0)
+}
+
+fn serialize_millis_opt<S: serde::Serializer>(
+    d: &Option<Duration>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match d {
+        Some(d) => serialize_millis(d, s),
+        None => s.serialize_none(),
+    }
+}
+
+impl ChatResponse {
+    /// Folds one event in; `elapsed` is the time since the request was sent.
+    pub fn record(&mut self, event: &ChatEvent, elapsed: Duration) {
+        match event {
+            ChatEvent::ContentDelta(delta) => {
+                self.time_to_first_token.get_or_insert(elapsed);
+                self.deltas += 1;
+                self.content.push_str(delta);
+            }
+            ChatEvent::ReasoningDelta(delta) => {
+                self.time_to_first_token.get_or_insert(elapsed);
+                self.deltas += 1;
+                self.reasoning.push_str(delta);
+            }
+            ChatEvent::ToolCallDelta(_) => {}
+            ChatEvent::ToolResult { .. } => self.content.clear(),
+            ChatEvent::Usage(usage) => {
+                let total = self.usage.get_or_insert_with(Usage::default);
+                total.prompt_tokens += usage.prompt_tokens;
+                total.completion_tokens += usage.completion_tokens;
+            }
+            ChatEvent::Timings(timings) => self.timings = Some(timings.clone()),
+            ChatEvent::FinishReason(reason) => self.finish_reason = Some(reason.clone()),
+            ChatEvent::Done => {}
+        }
+        self.latency = elapsed;
+    }
+
+    /// Whether generation stopped at the token limit rather than on its own.
+    pub fn truncated(&self) -> bool {
+        self.finish_reason.as_deref() == Some("length")
+    }
+
+    /// Logs the response's token counts and timings.
+    pub(crate) fn log_stats(&self) {
+        if let Some(usage) = &self.usage {
+            info!(
+                prompt_tokens = usage.prompt_tokens,
+                completion_tokens = usage.completion_tokens,
+                "Token usage"
+            );
+        }
+        if let Some(t) = &self.timings {
+            info!(
+                prompt_n = t.prompt_n,
+                prompt_ms = t.prompt_ms,
+                predicted_n = t.predicted_n,
+                predicted_ms = t.predicted_ms,
+                "Server timings"
+            );
+        }
+        info!(
+            finish_reason = self.finish_reason.as_deref().unwrap_or("-"),
+            ttft_ms = self.time_to_first_token.map(|d| d.as_millis() as u64),
+            latency_ms = self.latency.as_millis() as u64,
+            reasoning_chars = self.reasoning.len(),
+            "Completion finished"
+        );
+        if self.truncated() {
+            warn!("Response was cut off at the token limit; raise --max-tokens to get the rest");
+        }
+    }
+}
+
+// ------ Client ------
+
+/// Upper bound on model/tool round trips for a single request.
+const MAX_TOOL_ROUNDS: usize = 8;
+
+/// An OpenAI-compatible chat client.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use simple_llm_query::{ChatEvent, ChatMessage, ChatRequest, ContentPart, HttpTransport, LlmClient};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let endpoint = "http://localhost:8080/v1/chat/completions".parse()?;
+/// let client = LlmClient::new(HttpTransport::new(), endpoint);
+/// let request = ChatRequest::new()
+///     .stream(true)
+///     .message(ChatMessage::user(vec![ContentPart::text("Hello!")]));
+///
+/// let mut events = client.chat_stream(request);
+/// while let Some(event) = events.next().await {
+///     if let ChatEvent::ContentDelta(text) = event? {
+///         print!("{}", text);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct LlmClient<T: LlmTransport> {
+    transport: T,
+    endpoint: Url,
+    tools: ToolRegistry,
+}
+
+impl<T: LlmTransport> LlmClient<T> {
+    /// A client that sends chat requests to `endpoint`, a `/v1/chat/completions` URL.
+    pub fn new(transport: T, endpoint: Url) -> Self {
+        Self {
+            transport,
+            endpoint,
+            tools: ToolRegistry::new(),
+        }
+    }
+
+    /// Lets the model call these tools; they run locally until it gives a final answer.
+    pub fn with_tools(mut self, tools: ToolRegistry) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Lists the models the server offers.
+    pub async fn models(&self) -> Result<Vec<ModelInfo>> {
+        let json = self.transport.get_json(&models_url(&self.endpoint)).await?;
+        let data = json
+            .get("data")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Error::protocol("models response has no `data` array"))?;
+        Ok(data.iter().filter_map(ModelInfo::from_json).collect())
+    }
+
+    /// Sends the request and waits for the complete response.
+    pub async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let started = Instant::now();
+        let mut response = ChatResponse::default();
+        let mut events = self.chat_stream(request);
+        while let Some(event) = events.next().await {
+            response.record(&event?, started.elapsed());
+        }
+        Ok(response)
+    }
+
+    /// Sends the request and yields its events. Tool calls are run locally
+    /// and answered until the model replies without one, then `Done` is yielded.
+    pub fn chat_stream(&self, mut request: ChatRequest) -> ChatEvents<'_> {
+        Box::pin(async_stream::try_stream! {
+            if !self.tools.is_empty() {
+                request.tools = self.tools.specs();
+            }
+            let mut finished = false;
+            for _ in 0..MAX_TOOL_ROUNDS {
+                let mut text = String::new();
+                let mut tool_calls: Vec<ToolCall> = Vec::new();
+                {
+                    let events = self.complete(&request);
+                    pin_mut!(events);
+                    while let Some(event) = events.next().await {
+                        let event = event?;
+                        match &event {
+                            ChatEvent::ContentDelta(delta) => text.push_str(delta),
+                            ChatEvent::ToolCallDelta(delta) => delta.merge_into(&mut tool_calls),
+                            _ => {}
+                        }
+                        yield event;
+                    }
+                }
+                if tool_calls.is_empty() {
+                    finished = true;
+                    break;
+                }
+                request.messages.push(ChatMessage::assistant_tool_calls(text, tool_calls.clone()));
+                for call in tool_calls {
+                    info!(tool = %call.function.name, arguments = %call.function.arguments, "Calling tool");
+                    let output = self.tools.call(&call).await;
+                    request.messages.push(ChatMessage::tool(call.id.clone(), output.clone()));
+                    yield ChatEvent::ToolResult {
+                        id: call.id,
+                        name: call.function.name,
+                        output,
+                    };
+                }
+            }
+            if !finished {
+                Err(Error::tool(format!("model still calling tools after {} rounds", MAX_TOOL_ROUNDS)))?;
+            }
+            yield ChatEvent::Done;
+        })
+    }
+
+    /// Sends one request and yields its events, without handling tool calls.
+    fn complete<'a>(
+        &'a self,
+        request: &'a ChatRequest,
+    ) -> impl Stream<Item = Result<ChatEvent>> + 'a {
+        async_stream::try_stream! {
+            let stream = self.transport.send(&self.endpoint, request).await?;
+            pin_mut!(stream);
+
+            if !request.stream {
+                let mut buffer = Vec::new();
+                while let Some(item) = stream.next().await {
+                    buffer.extend_from_slice(&item?);
+                }
+                let response: CompletionResponse = serde_json::from_slice(&buffer)
+                    .map_err(|e| Error::protocol(format!("invalid completion response: {}", e)))?;
+                let choice = response
+                    .choices
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| Error::protocol("completion response has no choices"))?;
+                if let Some(reasoning) = choice.message.reasoning_content {
+                    yield ChatEvent::ReasoningDelta(reasoning);
+                }
+                if let Some(content) = choice.message.content {
+                    yield ChatEvent::ContentDelta(content);
+                }
+                for (index, call) in choice.message.tool_calls.iter().enumerate() {
+                    yield ChatEvent::ToolCallDelta((index, call).into());
+                }
+                if let Some(usage) = response.usage {
+                    yield ChatEvent::Usage(usage);
+                }
+                if let Some(timings) = response.timings {
+                    yield ChatEvent::Timings(timings);
+                }
+                if let Some(reason) = choice.finish_reason {
+                    yield ChatEvent::FinishReason(reason);
+                }
+            } else {
+                let events = sse::decode(stream);
+                pin_mut!(events);
+                while let Some(event) = events.next().await {
+                    let event = event?;
+                    if event.data.trim() == "[DONE]" {
+                        break;
+                    }
+                    if event.event == "error" {
+                        let json = serde_json::from_str(&event.data).unwrap_or(Value::Null);
+                        Err(stream_error(&json, &event.data))?;
+                    }
+                    let json: Value = serde_json::from_str(&event.data).map_err(|e| {
+                        let data: String = event.data.chars().take(200).collect();
+                        Error::protocol(format!("malformed stream event `{}`: {}", data, e))
+                    })?;
+                    if json.get("error").is_some_and(|e| !e.is_null()) {
+                        Err(stream_error(&json, &event.data))?;
+                    }
+                    for event in chunk_events(&json) {
+                        yield event;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An error the server sent after the stream had started with HTTP 200, either
+/// as an `error:` event (llama.cpp) or as `data: {"error": ...}` (OpenAI, vLLM).
+/// A numeric `code` in the error range stands in for the status, otherwise 500.
+fn stream_error(json: &Value, data: &str) -> Error {
+    let error = json.get("error").unwrap_or(json);
+    let status = error
+        .get("code")
+        .and_then(Value::as_u64)
+        .filter(|code| (400..600).contains(code))
+        .unwrap_or(500);
+    ApiError::from_body(status as u16, data).into()
+}
+
+/// Turns one streamed `chat.completion.chunk` into events.
+fn chunk_events(json: &Value) -> Vec<ChatEvent> {
+    let mut events = Vec::new();
+    let delta = json.pointer("/choices/0/delta");
+    let text = |key: &str| delta.and_then(|d| d.get(key)).and_then(Value::as_str);
+    if let Some(reasoning) = text("reasoning_content").or_else(|| text("reasoning")) {
+        events.push(ChatEvent::ReasoningDelta(reasoning.to_string()));
+    }
+    if let Some(content) = text("content") {
+        events.push(ChatEvent::ContentDelta(content.to_string()));
+    }
+    if let Some(calls) = delta
+        .and_then(|d| d.get("tool_calls"))
+        .and_then(Value::as_array)
+    {
+        events.extend(
+            calls
+                .iter()
+                .filter_map(|c| ToolCallDelta::deserialize(c).ok())
+                .map(ChatEvent::ToolCallDelta),
+        );
+    }
+    if let Some(usage) = json.get("usage").and_then(|u| Usage::deserialize(u).ok()) {
+        events.push(ChatEvent::Usage(usage));
+    }
+    if let Some(timings) = json
+        .get("timings")
+        .and_then(|t| Timings::deserialize(t).ok())
+    {
+        events.push(ChatEvent::Timings(timings));
+    }
+    if let Some(reason) = json
+        .pointer("/choices/0/finish_reason")
+        .and_then(Value::as_str)
+    {
+        events.push(ChatEvent::FinishReason(reason.to_string()));
+    }
+    events
+}
@@ -0,0 +1,124 @@
+//! The config file and its named profiles.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{Error, Result, SamplingParams, auth::Secret};
+
+// ------ Config File ------
+
+/// Contents of `config.toml`.
+///
+/// ```toml
+/// default_profile = "local"
+///
+/// [profiles.local]
+/// endpoint = "http://localhost:8080/v1/chat/completions"
+/// model = "gemma-3-12b-it"
+/// api_key_env = "LOCAL_LLM_KEY"
+/// system = "Be concise."
+/// headers = { "X-Team" = "search" }
+///
+/// [profiles.local.sampling]
+/// temperature = 0.2
+/// ```
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// A named set of defaults, selected with `--profile`.
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// The chat completions URL.
+    pub endpoint: Option<String>,
+    /// The model to request.
+    pub model: Option<String>,
+    /// Name of the environment variable holding the API key (never the key itself).
+    pub api_key_env: Option<String>,
+    /// Credentials for HTTP basic auth.
+    pub basic_auth: Option<BasicAuthConfig>,
+    /// Extra headers sent with every request.
+    #[serde(default)]
+    pub headers: BTreeMap<String, Secret>,
+    /// System prompt sent before the conversation.
+    pub system: Option<String>,
+    /// Whether to ask the model to review its first answer.
+    pub review: Option<bool>,
+    /// Instruction used for the review step instead of the built-in one.
+    pub review_prompt: Option<String>,
+    /// Sampling parameters sent with every request.
+    #[serde(default)]
+    pub sampling: SamplingParams,
+}
+
+/// Basic auth credentials; the password is read from an environment variable.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BasicAuthConfig {
+    /// The user name.
+    pub username: String,
+    /// Name of the environment variable holding the password.
+    pub password_env: String,
+}
+
+impl Config {
+    /// `config.toml` in the user config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("simple-llm-query").join("config.toml"))
+    }
+
+    /// Loads an explicitly given config file, or the default one if it exists.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Self::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Self::default());
+            }
+            Err(e) => {
+                return Err(Error::config(format!(
+                    "could not read config {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
+        toml::from_str(&text)
+            .map_err(|e| Error::config(format!("invalid config {}: {}", path.display(), e)))
+    }
+
+    /// The requested profile, else the default profile, else an empty one.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        let Some(name) = name.or(self.default_profile.as_deref()) else {
+            return Ok(Profile::default());
+        };
+        self.profiles.get(name).cloned().ok_or_else(|| {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            Error::config(format!(
+                "unknown profile `{}` (configured: {})",
+                name,
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            ))
+        })
+    }
+}
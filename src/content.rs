@@ -0,0 +1,68 @@
+//! Building the user message content from a prompt and images.
+
+use std::path::Path;
+
+use base64::Engine;
+
+use crate::{
+    Result, images,
+    message::{ContentPart, ContentType, ImageContent, ImageUrl},
+    prompt::Prompt,
+};
+
+// ------ Image Encoding Trait ------
+
+/// Turns a local image file into a URL the server accepts, usually a data URL.
+pub trait ImageEncoder {
+    /// Reads the file at `path` and returns the URL to send in its place.
+    fn encode_path(&self, path: &Path) -> Result<String>;
+}
+
+/// Sends images as base64 data URLs, without their metadata but otherwise as
+/// they are. Formats that servers rarely accept are converted first; see
+/// [`images::read_supported`] and [`images::strip_metadata`].
+pub struct DataUrlEncoder;
+
+impl ImageEncoder for DataUrlEncoder {
+    fn encode_path(&self, path: &Path) -> Result<String> {
+        let (kind, bytes) = images::read_supported(path)?;
+        let bytes = images::strip_metadata(path, kind, bytes)?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&bytes);
+        Ok(format!("data:{};base64,{}", kind.mime(), encoded))
+    }
+}
+
+// ------ Content Builder ------
+
+/// The content parts of a user message: the prompt's text blocks followed by
+/// one part per image. Image URLs are passed through; local paths go through `encoder`.
+pub fn build_request_content(
+    prompt: &Prompt,
+    images: &[String],
+    encoder: &dyn ImageEncoder,
+) -> Result<Vec<ContentPart>> {
+    let mut parts: Vec<ContentPart> = prompt
+        .text_parts()
+        .into_iter()
+        .map(ContentPart::text)
+        .collect();
+    for image in images {
+        // URLs are already something the server can fetch or decode; only local files get encoded.
+        let url = if is_image_url(image) {
+            image.clone()
+        } else {
+            encoder.encode_path(Path::new(image))?
+        };
+        parts.push(ContentPart::Image(ImageContent {
+            content_type: ContentType::ImageUrl,
+            image_url: ImageUrl { url },
+        }));
+    }
+    Ok(parts)
+}
+
+fn is_image_url(image: &str) -> bool {
+    ["http://", "https://", "data:"]
+        .iter()
+        .any(|scheme| image.starts_with(scheme))
+}
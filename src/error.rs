@@ -0,0 +1,124 @@
+//! The error type shared by the whole crate.
+
+use std::{fmt, io};
+
+use crate::{structured::StructuredOutputError, transport::ApiError};
+
+/// Shorthand for results whose error is the crate's [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+// ------ Error ------
+
+/// Everything that can go wrong while building, sending or answering a request.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The server could not be reached or the connection broke: refused,
+    /// DNS, TLS or a timeout. Usually holds a `reqwest::Error`; other
+    /// transports put their own error here.
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    /// The server answered with a non-success HTTP status.
+    Status(ApiError),
+    /// The server's answer could not be understood, e.g. a malformed SSE frame
+    /// or a completion without choices.
+    Protocol(String),
+    /// A structured answer was not valid JSON or did not match its schema.
+    Structured(StructuredOutputError),
+    /// A local image could not be read, converted or encoded.
+    Image(String),
+    /// Reading or writing a file, stdin or stdout failed.
+    Io(io::Error),
+    /// Invalid settings or input: config files, profiles, prompt templates,
+    /// sessions, schemas and tool declarations.
+    Config(String),
+    /// A tool could not be run, or the model kept calling tools.
+    Tool(String),
+    /// The request was cancelled, e.g. with Ctrl-C.
+    Cancelled,
+}
+
+impl Error {
+    pub(crate) fn transport(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::Transport(source.into())
+    }
+
+    pub(crate) fn protocol(message: impl fmt::Display) -> Self {
+        Self::Protocol(message.to_string())
+    }
+
+    pub(crate) fn image(message: impl fmt::Display) -> Self {
+        Self::Image(message.to_string())
+    }
+
+    pub(crate) fn config(message: impl fmt::Display) -> Self {
+        Self::Config(message.to_string())
+    }
+
+    pub(crate) fn tool(message: impl fmt::Display) -> Self {
+        Self::Tool(message.to_string())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(e) => match e.downcast_ref::<reqwest::Error>() {
+                Some(e) if e.is_timeout() => write!(f, "request timed out: {}", e),
+                Some(e) if e.is_connect() => write!(f, "could not connect to the server: {}", e),
+                _ => write!(f, "connection failed: {}", e),
+            },
+            Self::Status(e) => e.fmt(f),
+            Self::Protocol(message) => write!(f, "unexpected response from server: {}", message),
+            Self::Structured(e) => e.fmt(f),
+            Self::Image(message) => write!(f, "image error: {}", message),
+            Self::Io(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                f.write_str("output closed before the answer was written")
+            }
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::Config(message) => f.write_str(message),
+            Self::Tool(message) => write!(f, "tool error: {}", message),
+            Self::Cancelled => f.write_str("cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(e) => Some(e.as_ref()),
+            Self::Status(e) => Some(e),
+            Self::Structured(e) => Some(e),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        // A body that arrived but could not be parsed is the server's fault, not the network's.
+        if e.is_decode() {
+            Self::Protocol(e.to_string())
+        } else {
+            Self::transport(e)
+        }
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(e: ApiError) -> Self {
+        Self::Status(e)
+    }
+}
+
+impl From<StructuredOutputError> for Error {
+    fn from(e: StructuredOutputError) -> Self {
+        Self::Structured(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
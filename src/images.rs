@@ -0,0 +1,349 @@
+//! Reading local images: format detection, HEIC/AVIF conversion, metadata
+//! removal, and optional resizing and re-encoding before upload.
+
+use std::{fs, io, io::Cursor, path::Path, process::Command};
+
+use base64::Engine;
+use clap::ValueEnum;
+use image::{
+    DynamicImage, ImageDecoder, ImageFormat, ImageReader,
+    codecs::{
+        jpeg::JpegEncoder,
+        png::{CompressionType, FilterType as PngFilter, PngEncoder},
+        webp::WebPEncoder,
+    },
+    imageops::FilterType,
+    metadata::Orientation,
+};
+
+use crate::{Error, ImageEncoder, Result};
+
+// ------ Format Detection ------
+
+/// Image container detected from a file's leading bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageKind {
+    /// PNG
+    Png,
+    /// JPEG
+    Jpeg,
+    /// GIF
+    Gif,
+    /// WebP
+    Webp,
+    /// Windows bitmap
+    Bmp,
+    /// TIFF
+    Tiff,
+    /// HEIC/HEIF, as taken by iPhones
+    Heic,
+    /// AVIF
+    Avif,
+}
+
+impl ImageKind {
+    /// Identifies the format from magic bytes, ignoring the file name.
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some(Self::Png),
+            [0xFF, 0xD8, 0xFF, ..] => Some(Self::Jpeg),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(Self::Gif),
+            [b'R', b'I', b'F', b'F', _, _, _, _, rest @ ..] if rest.starts_with(b"WEBP") => {
+                Some(Self::Webp)
+            }
+            [b'B', b'M', ..] if bytes.len() >= 26 => Some(Self::Bmp),
+            [b'I', b'I', 0x2A, 0x00, ..] | [b'M', b'M', 0x00, 0x2A, ..] => Some(Self::Tiff),
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => Self::sniff_ftyp(bytes),
+            _ => None,
+        }
+    }
+
+    /// HEIF and AVIF share the ISO-BMFF `ftyp` box; the brands tell them apart.
+    fn sniff_ftyp(bytes: &[u8]) -> Option<Self> {
+        let size = u32::from_be_bytes(bytes[..4].try_into().ok()?) as usize;
+        let ftyp = bytes.get(8..size.min(bytes.len()))?;
+        // Major brand, then (after the minor version) the compatible brands.
+        let brands = ftyp
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, b)| b);
+        let mut heif = false;
+        for brand in brands {
+            match brand {
+                b"avif" | b"avis" => return Some(Self::Avif),
+                b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" | b"mif1" | b"msf1" => {
+                    heif = true
+                }
+                _ => {}
+            }
+        }
+        heif.then_some(Self::Heic)
+    }
+
+    /// The MIME type, e.g. `image/png`.
+    pub fn mime(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+            Self::Bmp => "image/bmp",
+            Self::Tiff => "image/tiff",
+            Self::Heic => "image/heic",
+            Self::Avif => "image/avif",
+        }
+    }
+
+    /// Whether OpenAI-compatible servers accept this format as-is.
+    fn is_widely_supported(self) -> bool {
+        matches!(self, Self::Png | Self::Jpeg | Self::Gif | Self::Webp)
+    }
+}
+
+// ------ Loading and Conversion ------
+
+/// Reads an image file in a format the `image` crate can decode.
+///
+/// HEIC and AVIF have no pure-Rust decoder, so they go through ImageMagick and
+/// come back as JPEG. Anything that is not an image is rejected here, before a
+/// request is ever built.
+pub fn read_image(path: &Path) -> Result<(ImageKind, Vec<u8>)> {
+    let bytes = fs::read(path)
+        .map_err(|e| Error::image(format!("could not read {}: {}", path.display(), e)))?;
+    let kind = ImageKind::sniff(&bytes).ok_or_else(|| {
+        Error::image(format!(
+            "{} is not a supported image (expected PNG, JPEG, GIF, WebP, BMP, TIFF, HEIC or AVIF)",
+            path.display()
+        ))
+    })?;
+    match kind {
+        ImageKind::Heic | ImageKind::Avif => Ok((ImageKind::Jpeg, convert_external(path, kind)?)),
+        _ => Ok((kind, bytes)),
+    }
+}
+
+/// Like `read_image`, but also converts BMP and TIFF to PNG so any server can take the result.
+pub fn read_supported(path: &Path) -> Result<(ImageKind, Vec<u8>)> {
+    let (kind, bytes) = read_image(path)?;
+    if kind.is_widely_supported() {
+        return Ok((kind, bytes));
+    }
+    let decode_error = |e| Error::image(format!("could not decode {}: {}", path.display(), e));
+    let img = image::load_from_memory(&bytes).map_err(decode_error)?;
+    let mut out = Vec::new();
+    img.write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+        .map_err(decode_error)?;
+    Ok((ImageKind::Png, out))
+}
+
+fn convert_external(path: &Path, kind: ImageKind) -> Result<Vec<u8>> {
+    // ImageMagick 7 ships `magick`; version 6 only has `convert`.
+    for program in ["magick", "convert"] {
+        let output = match Command::new(program).arg(path).arg("jpeg:-").output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::image(format!("could not run {}: {}", program, e))),
+        };
+        if !output.status.success() || output.stdout.is_empty() {
+            return Err(Error::image(format!(
+                "could not convert {} with {}: {}",
+                path.display(),
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        return Ok(output.stdout);
+    }
+    Err(Error::image(format!(
+        "{} is {}; install ImageMagick (`magick`) to convert it, or convert it to JPEG first",
+        path.display(),
+        kind.mime()
+    )))
+}
+
+// ------ Metadata ------
+
+/// JPEG segments that only carry metadata: EXIF and XMP (APP1), IPTC (APP13)
+/// and comments. ICC colour profiles (APP2) are kept.
+const JPEG_METADATA_MARKERS: [u8; 3] = [0xE1, 0xED, 0xFE];
+
+/// Quality for JPEGs re-encoded to apply their orientation; high enough not to
+/// lose visible detail.
+const UPRIGHT_JPEG_QUALITY: u8 = 95;
+
+/// PNG chunks that only carry metadata.
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 5] = [b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+/// Removes EXIF (including GPS location), XMP, IPTC and text metadata from JPEG
+/// and PNG files without touching the pixels. Other formats are returned as they are.
+///
+/// A JPEG whose EXIF orientation rotates or flips it is re-encoded upright
+/// instead, since dropping the tag would turn it sideways.
+pub fn strip_metadata(path: &Path, kind: ImageKind, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    let stripped = match kind {
+        ImageKind::Jpeg if has_orientation(&bytes) => {
+            ResizingEncoder::new(None, UPRIGHT_JPEG_QUALITY, OutputFormat::Jpeg)
+                .preprocess(&bytes)
+                .map_err(|e| e.to_string())
+        }
+        ImageKind::Jpeg => strip_jpeg(&bytes),
+        ImageKind::Png => strip_png(&bytes),
+        _ => return Ok(bytes),
+    };
+    stripped.map_err(|e| {
+        Error::image(format!(
+            "could not remove metadata from {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+fn has_orientation(bytes: &[u8]) -> bool {
+    let orientation = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_decoder().ok())
+        .and_then(|mut decoder| decoder.orientation().ok());
+    !matches!(orientation, None | Some(Orientation::NoTransforms))
+}
+
+fn strip_jpeg(bytes: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    let truncated = || "truncated JPEG".to_string();
+    let mut out = bytes[..2].to_vec();
+    let mut pos = 2;
+    loop {
+        let marker = *bytes.get(pos + 1).ok_or_else(truncated)?;
+        if bytes[pos] != 0xFF {
+            return Err(format!("expected a JPEG marker at byte {}", pos));
+        }
+        // Markers may be padded with any number of fill bytes.
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        // Start of scan: the compressed image data follows up to the end.
+        if marker == 0xDA {
+            out.extend_from_slice(&bytes[pos..]);
+            return Ok(out);
+        }
+        let len = bytes.get(pos + 2..pos + 4).ok_or_else(truncated)?;
+        let end = pos + 2 + u16::from_be_bytes([len[0], len[1]]) as usize;
+        let segment = bytes.get(pos..end).ok_or_else(truncated)?;
+        if !JPEG_METADATA_MARKERS.contains(&marker) {
+            out.extend_from_slice(segment);
+        }
+        pos = end;
+    }
+}
+
+fn strip_png(bytes: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    let truncated = || "truncated PNG".to_string();
+    let mut out = bytes[..8].to_vec();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let header = bytes.get(pos..pos + 8).ok_or_else(truncated)?;
+        let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        // Length, type, data and CRC.
+        let end = pos + 12 + len;
+        let chunk = bytes.get(pos..end).ok_or_else(truncated)?;
+        if !PNG_METADATA_CHUNKS.iter().any(|t| &header[4..] == *t) {
+            out.extend_from_slice(chunk);
+        }
+        if &header[4..] == b"IEND" {
+            break;
+        }
+        pos = end;
+    }
+    Ok(out)
+}
+
+// ------ Preprocessing Encoder ------
+
+/// Output format for re-encoded images.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum OutputFormat {
+    /// Lossy, at the chosen quality
+    #[default]
+    Jpeg,
+    /// Lossless
+    Png,
+    /// Lossless WebP
+    Webp,
+}
+
+impl OutputFormat {
+    fn mime(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Png => "image/png",
+            OutputFormat::Webp => "image/webp",
+        }
+    }
+}
+
+/// Decodes the image, applies its EXIF orientation, shrinks it to fit `max_edge`
+/// and re-encodes it. Re-encoding drops all metadata, including GPS tags.
+pub struct ResizingEncoder {
+    max_edge: Option<u32>,
+    /// JPEG quality (1-100). PNG and WebP output is lossless and ignores it.
+    quality: u8,
+    format: OutputFormat,
+}
+
+impl ResizingEncoder {
+    /// `quality` is clamped to 1-100.
+    pub fn new(max_edge: Option<u32>, quality: u8, format: OutputFormat) -> Self {
+        Self {
+            max_edge,
+            quality: quality.clamp(1, 100),
+            format,
+        }
+    }
+
+    fn preprocess(&self, bytes: &[u8]) -> image::ImageResult<Vec<u8>> {
+        let mut decoder = ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()?
+            .into_decoder()?;
+        let orientation = decoder.orientation()?;
+        let mut img = DynamicImage::from_decoder(decoder)?;
+        img.apply_orientation(orientation);
+
+        if let Some(max) = self.max_edge
+            && (img.width() > max || img.height() > max)
+        {
+            img = img.resize(max, max, FilterType::Lanczos3);
+        }
+
+        let mut out = Vec::new();
+        match self.format {
+            OutputFormat::Jpeg => {
+                // JPEG has no alpha channel.
+                let rgb = DynamicImage::ImageRgb8(img.to_rgb8());
+                rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut out, self.quality))?;
+            }
+            OutputFormat::Png => img.write_with_encoder(PngEncoder::new_with_quality(
+                &mut out,
+                CompressionType::Best,
+                PngFilter::Adaptive,
+            ))?,
+            OutputFormat::Webp => {
+                let rgba = DynamicImage::ImageRgba8(img.to_rgba8());
+                rgba.write_with_encoder(WebPEncoder::new_lossless(&mut out))?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl ImageEncoder for ResizingEncoder {
+    fn encode_path(&self, path: &Path) -> Result<String> {
+        let (_, bytes) = read_image(path)?;
+        let processed = self
+            .preprocess(&bytes)
+            .map_err(|e| Error::image(format!("could not process {}: {}", path.display(), e)))?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&processed);
+        Ok(format!("data:{};base64,{}", self.format.mime(), encoded))
+    }
+}
@@ -0,0 +1,51 @@
+//! A small client for OpenAI-compatible chat completion servers.
+//!
+//! [`LlmClient`] sends a [`ChatRequest`] through an [`LlmTransport`] and
+//! yields the answer as a stream of [`ChatEvent`]s, running any registered
+//! tools along the way. [`HttpTransport`] talks to a real server; other
+//! transports can be plugged in for testing.
+//!
+//! The `simple-llm-query` binary is a thin wrapper around [`cli::run`].
+//!
+//! The default `mock` feature adds `mock::MockTransport` for tests and
+//! `mock_server`, a fake server behind the `mock-server` subcommand.
+
+#![warn(missing_docs)]
+
+pub mod auth;
+pub mod cli;
+pub mod client;
+pub mod config;
+pub mod content;
+pub mod error;
+pub mod images;
+pub mod markdown;
+pub mod message;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(feature = "mock")]
+pub mod mock_server;
+pub mod models;
+pub mod output;
+pub mod prompt;
+mod repl;
+pub mod request;
+pub mod session;
+pub mod sse;
+pub mod stats;
+pub mod structured;
+pub mod tools;
+pub mod transport;
+
+pub use client::{
+    ChatEvent, ChatEvents, ChatResponse, FunctionDelta, LlmClient, Timings, ToolCallDelta, Usage,
+};
+pub use content::{DataUrlEncoder, ImageEncoder, build_request_content};
+pub use error::{Error, Result};
+pub use message::{
+    ChatMessage, ContentPart, ContentType, FunctionCall, ImageContent, ImageUrl, Role, TextContent,
+    ToolCall,
+};
+pub use models::ModelInfo;
+pub use request::{ChatRequest, SamplingParams, StreamOptions};
+pub use transport::{ApiError, HttpTransport, LlmTransport};
@@ -0,0 +1,705 @@
+//! Streaming Markdown renderer for terminal output.
+//!
+//! Text is rendered as it arrives: inline emphasis is styled on the fly, while
+//! constructs that need the whole line or block (fences, code lines, rules and
+//! tables) are held back until they are complete.
+
+use std::sync::OnceLock;
+
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Theme, ThemeSet},
+    parsing::SyntaxSet,
+    util::as_24_bit_terminal_escaped,
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const ITALIC: &str = "\x1b[3m";
+const STRIKE: &str = "\x1b[9m";
+const CODE: &str = "\x1b[36m";
+const MARKER: &str = "\x1b[33m";
+const HEADING_1: &str = "\x1b[1;4;35m";
+const HEADING: &str = "\x1b[1;35m";
+const RULE_WIDTH: usize = 40;
+const THEME: &str = "base16-ocean.dark";
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    &THEMES.get_or_init(ThemeSet::load_defaults).themes[THEME]
+}
+
+// ------ Renderer ------
+
+/// How the current line is rendered, once its first characters tell.
+enum Line {
+    /// Styled and written as it arrives, after a block prefix such as a bullet.
+    Streaming,
+    /// Held until the line is complete: fences, code, table rows and rules.
+    Whole,
+}
+
+/// Renders Markdown deltas to ANSI-styled text.
+#[derive(Default)]
+pub struct MarkdownRenderer {
+    /// Characters of the current line not yet rendered.
+    pending: String,
+    line: Option<Line>,
+    inline: Inline,
+    code: Option<HighlightLines<'static>>,
+    table: Vec<String>,
+}
+
+impl MarkdownRenderer {
+    /// A renderer at the start of a document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders the next delta; returns what can be shown so far.
+    pub fn push(&mut self, delta: &str) -> String {
+        let mut out = String::new();
+        for c in delta.chars() {
+            match c {
+                '\n' => self.end_line(&mut out),
+                '\r' => {}
+                c if matches!(self.line, Some(Line::Streaming)) => self.inline.push(c, &mut out),
+                c => {
+                    self.pending.push(c);
+                    if self.line.is_none() {
+                        self.classify(&mut out);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Renders whatever is still held back and resets the renderer.
+    pub fn finish(&mut self) -> String {
+        let mut out = String::new();
+        if self.line.is_some() || !self.pending.is_empty() {
+            self.end_line(&mut out);
+        }
+        self.flush_table(&mut out);
+        if self.code.take().is_some() {
+            out.push_str(RESET);
+        }
+        out
+    }
+
+    /// Decides how to render the current line from its first characters; returns
+    /// without deciding while they are still ambiguous.
+    fn classify(&mut self, out: &mut String) {
+        if self.code.is_some() {
+            self.line = Some(Line::Whole);
+            return;
+        }
+        let indent = self.pending.len() - self.pending.trim_start().len();
+        let text = &self.pending[indent..];
+        let Some(first) = text.chars().next() else {
+            return;
+        };
+        let margin = " ".repeat(indent);
+        match first {
+            '`' | '~' if text.len() < 3 && text.chars().all(|c| c == first) => return,
+            '`' | '~' if text.starts_with("```") || text.starts_with("~~~") => {
+                self.line = Some(Line::Whole);
+                return;
+            }
+            '|' => {
+                self.line = Some(Line::Whole);
+                return;
+            }
+            '#' => {
+                let level = text.chars().take_while(|&c| c == '#').count();
+                match text[level..].chars().next() {
+                    None => return,
+                    Some(' ') if level <= 6 => {
+                        let style = if level == 1 { HEADING_1 } else { HEADING };
+                        self.start_streaming(out, "", style, indent + level + 1);
+                        return;
+                    }
+                    Some(_) => {}
+                }
+            }
+            '-' | '*' | '+' | '_' => {
+                // A rule, or not yet known whether it is a list item or emphasis.
+                if text.chars().filter(|&c| c != ' ').all(|c| c == first) {
+                    return;
+                }
+                if first != '_' && text[1..].starts_with(' ') {
+                    let bullet = format!("{}{}•{} ", margin, MARKER, RESET);
+                    self.start_streaming(out, &bullet, "", indent + 2);
+                    return;
+                }
+            }
+            '0'..='9' => {
+                let digits = text.chars().take_while(char::is_ascii_digit).count();
+                let rest = &text[digits..];
+                if rest.is_empty() || rest == "." || rest == ")" {
+                    return;
+                }
+                if rest.starts_with(". ") || rest.starts_with(") ") {
+                    let number = format!("{}{}{}{} ", margin, MARKER, &text[..digits + 1], RESET);
+                    self.start_streaming(out, &number, "", indent + digits + 2);
+                    return;
+                }
+            }
+            '>' => {
+                if text.len() < 2 {
+                    return;
+                }
+                let skip = if text.starts_with("> ") { 2 } else { 1 };
+                let bar = format!("{}{}│{} ", margin, DIM, RESET);
+                self.start_streaming(out, &bar, ITALIC, indent + skip);
+                return;
+            }
+            _ => {}
+        }
+        // A plain paragraph line, indentation included.
+        self.start_streaming(out, "", "", 0);
+    }
+
+    /// Writes the block prefix and streams the rest of the line from byte `skip` on.
+    fn start_streaming(
+        &mut self,
+        out: &mut String,
+        prefix: &str,
+        style: &'static str,
+        skip: usize,
+    ) {
+        self.flush_table(out);
+        self.line = Some(Line::Streaming);
+        out.push_str(prefix);
+        out.push_str(style);
+        self.inline = Inline::new(style);
+        let rest = std::mem::take(&mut self.pending);
+        for c in rest[skip.min(rest.len())..].chars() {
+            self.inline.push(c, out);
+        }
+    }
+
+    fn end_line(&mut self, out: &mut String) {
+        let line = std::mem::take(&mut self.pending);
+        match self.line.take() {
+            Some(Line::Streaming) => {
+                self.inline.end(out);
+                out.push('\n');
+            }
+            _ if self.code.is_some() => self.code_line(&line, out),
+            _ => self.whole_line(line, out),
+        }
+    }
+
+    fn whole_line(&mut self, line: String, out: &mut String) {
+        let text = line.trim();
+        if text.starts_with('|') {
+            self.table.push(line);
+            return;
+        }
+        self.flush_table(out);
+        if let Some(lang) = text
+            .strip_prefix("```")
+            .or_else(|| text.strip_prefix("~~~"))
+        {
+            let syntaxes = syntaxes();
+            let syntax = syntaxes
+                .find_syntax_by_token(lang.trim())
+                .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+            self.code = Some(HighlightLines::new(syntax, theme()));
+            out.push_str(&format!("{}{}{}\n", DIM, line, RESET));
+        } else if is_rule(text) {
+            out.push_str(&format!("{}{}{}\n", DIM, "─".repeat(RULE_WIDTH), RESET));
+        } else if text.is_empty() {
+            out.push('\n');
+        } else {
+            // Too short to classify before it ended, such as `-` or `42`.
+            self.pending = line;
+            self.start_streaming(out, "", "", 0);
+            self.inline.end(out);
+            self.line = None;
+            out.push('\n');
+        }
+    }
+
+    fn code_line(&mut self, line: &str, out: &mut String) {
+        let text = line.trim();
+        if text.starts_with("```") || text.starts_with("~~~") {
+            self.code = None;
+            out.push_str(&format!("{}{}{}\n", DIM, line, RESET));
+            return;
+        }
+        let Some(highlighter) = &mut self.code else {
+            return;
+        };
+        let with_newline = format!("{}\n", line);
+        match highlighter.highlight_line(&with_newline, syntaxes()) {
+            Ok(ranges) => {
+                out.push_str(as_24_bit_terminal_escaped(&ranges, false).trim_end_matches('\n'));
+                out.push_str(RESET);
+                out.push('\n');
+            }
+            Err(_) => out.push_str(&with_newline),
+        }
+    }
+
+    fn flush_table(&mut self, out: &mut String) {
+        if !self.table.is_empty() {
+            out.push_str(&render_table(&std::mem::take(&mut self.table)));
+        }
+    }
+}
+
+/// `---`, `***` or `___`, optionally with spaces between.
+fn is_rule(text: &str) -> bool {
+    let marks: Vec<char> = text.chars().filter(|&c| c != ' ').collect();
+    marks.len() >= 3 && matches!(marks[0], '-' | '*' | '_') && marks.iter().all(|&c| c == marks[0])
+}
+
+// ------ Inline Styles ------
+
+/// Styles emphasis, strikethrough and code spans character by character.
+///
+/// Runs of `*`, `_` and `~` are held until the next character shows whether
+/// they open or close a span. Once a span opens, output is held until every
+/// open span has closed; if the line ends first, the opening marker was plain
+/// text after all and the rest of the line is rendered again.
+#[derive(Default, Clone)]
+struct Inline {
+    base: &'static str,
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    code: bool,
+    held: String,
+    prev: Option<char>,
+    /// Visible characters written, for aligning table cells.
+    width: usize,
+    open: Option<Box<OpenSpan>>,
+}
+
+/// A span that has opened but not yet closed.
+#[derive(Clone)]
+struct OpenSpan {
+    /// The state just before the opening marker was resolved.
+    before: Inline,
+    /// Characters received since then, starting with the one that resolved it.
+    raw: String,
+    /// Their rendering, shown once the span closes.
+    output: String,
+}
+
+impl Inline {
+    fn new(base: &'static str) -> Self {
+        Self {
+            base,
+            ..Self::default()
+        }
+    }
+
+    /// Styles a complete piece of text; returns it with its visible width.
+    fn render(text: &str) -> (String, usize) {
+        let mut inline = Self::new("");
+        let mut out = String::new();
+        for c in text.chars() {
+            inline.push(c, &mut out);
+        }
+        inline.end(&mut out);
+        (out, inline.width)
+    }
+
+    fn styled(&self) -> bool {
+        self.bold || self.italic || self.strike || self.code
+    }
+
+    fn push(&mut self, c: char, out: &mut String) {
+        self.feed(Some(c), out);
+    }
+
+    /// Renders `c`, or resolves the held markers at the end of the line for
+    /// `None`, holding the output back while a span is open.
+    fn feed(&mut self, c: Option<char>, out: &mut String) {
+        if let Some(mut open) = self.open.take() {
+            open.raw.extend(c);
+            self.step(c, &mut open.output);
+            if self.styled() {
+                self.open = Some(open);
+            } else {
+                out.push_str(&open.output);
+            }
+            return;
+        }
+        // Only a held marker run or a backtick can open a span.
+        let before = (c == Some('`') || !self.held.is_empty()).then(|| self.clone());
+        let mut output = String::new();
+        self.step(c, &mut output);
+        match before {
+            Some(before) if self.styled() => {
+                self.open = Some(Box::new(OpenSpan {
+                    before,
+                    raw: c.into_iter().collect(),
+                    output,
+                }))
+            }
+            _ => out.push_str(&output),
+        }
+    }
+
+    fn step(&mut self, c: Option<char>, out: &mut String) {
+        let Some(c) = c else {
+            self.resolve(None, out);
+            return;
+        };
+        if self.code {
+            if c == '`' {
+                self.code = false;
+                self.apply(out);
+            } else {
+                self.emit(c, out);
+            }
+            return;
+        }
+        if matches!(c, '*' | '_' | '~') {
+            if self.held.chars().next().is_some_and(|h| h != c) {
+                self.resolve(Some(c), out);
+            }
+            self.held.push(c);
+            return;
+        }
+        self.resolve(Some(c), out);
+        if c == '`' {
+            self.code = true;
+            self.apply(out);
+        } else {
+            self.emit(c, out);
+        }
+    }
+
+    /// Closes the line: held markers are resolved, spans that never closed
+    /// are rendered as plain text, and styles reset.
+    fn end(&mut self, out: &mut String) {
+        self.feed(None, out);
+        while let Some(open) = self.open.take() {
+            *self = open.before;
+            let mut raw = open.raw.chars();
+            if self.held.is_empty() {
+                // The span was a code span opened by this backtick.
+                raw.next();
+                self.emit('`', out);
+            } else {
+                for mark in std::mem::take(&mut self.held).chars() {
+                    self.emit(mark, out);
+                }
+            }
+            for c in raw {
+                self.feed(Some(c), out);
+            }
+            self.feed(None, out);
+        }
+        if self.bold || self.italic || self.strike || self.code || !self.base.is_empty() {
+            out.push_str(RESET);
+        }
+        self.bold = false;
+        self.italic = false;
+        self.strike = false;
+        self.code = false;
+    }
+
+    /// Interprets the held marker run, now that the following character is known.
+    fn resolve(&mut self, next: Option<char>, out: &mut String) {
+        let Some(mark) = self.held.chars().next() else {
+            return;
+        };
+        let count = self.held.chars().count();
+        self.held.clear();
+        let before = self.prev.is_some_and(|c| !c.is_whitespace());
+        let after = next.is_some_and(|c| !c.is_whitespace());
+        // `_` only counts at word boundaries, so snake_case stays intact.
+        let boundary = mark != '_'
+            || (!self.prev.is_some_and(char::is_alphanumeric)
+                || !next.is_some_and(char::is_alphanumeric));
+        let mut toggled = false;
+        let mut literal = 0;
+        let mut toggle = |on: &mut bool| {
+            let allowed = if *on { before } else { after } && boundary;
+            if allowed {
+                *on = !*on;
+                toggled = true;
+            }
+            allowed
+        };
+        match (mark, count) {
+            ('~', 2) => {
+                if !toggle(&mut self.strike) {
+                    literal = 2;
+                }
+            }
+            ('~', n) => literal = n,
+            (_, 1) => {
+                if !toggle(&mut self.italic) {
+                    literal = 1;
+                }
+            }
+            (_, 2) => {
+                if !toggle(&mut self.bold) {
+                    literal = 2;
+                }
+            }
+            (_, 3) => {
+                if !(toggle(&mut self.bold) && toggle(&mut self.italic)) {
+                    literal = 3;
+                }
+            }
+            (_, n) => literal = n,
+        }
+        if toggled {
+            self.apply(out);
+        }
+        for _ in 0..literal {
+            self.emit(mark, out);
+        }
+    }
+
+    fn emit(&mut self, c: char, out: &mut String) {
+        out.push(c);
+        self.prev = Some(c);
+        self.width += 1;
+    }
+
+    /// Resets and re-applies the styles that are currently on.
+    fn apply(&self, out: &mut String) {
+        out.push_str(RESET);
+        out.push_str(self.base);
+        for (on, style) in [
+            (self.bold, BOLD),
+            (self.italic, ITALIC),
+            (self.strike, STRIKE),
+            (self.code, CODE),
+        ] {
+            if on {
+                out.push_str(style);
+            }
+        }
+    }
+}
+
+// ------ Tables ------
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|')
+        .map(|cell| cell.trim().to_string())
+        .collect()
+}
+
+/// Parses a `|---|:--:|` delimiter row into column alignments.
+fn delimiter_row(cells: &[String]) -> Option<Vec<Align>> {
+    cells
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Align::Center,
+                (false, true) => Align::Right,
+                _ => Align::Left,
+            })
+        })
+        .collect()
+}
+
+/// Lays out buffered table rows with aligned columns and a bold header.
+fn render_table(lines: &[String]) -> String {
+    let mut rows: Vec<Vec<String>> = lines.iter().map(|l| split_row(l)).collect();
+    let mut aligns = vec![];
+    let mut header = false;
+    if rows.len() > 1
+        && let Some(found) = delimiter_row(&rows[1])
+    {
+        aligns = found;
+        header = true;
+        rows.remove(1);
+    }
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let cells: Vec<Vec<(String, usize)>> = rows
+        .iter()
+        .map(|row| {
+            (0..columns)
+                .map(|i| Inline::render(row.get(i).map_or("", String::as_str)))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| cells.iter().map(|row| row[i].1).max().unwrap_or(0))
+        .collect();
+
+    let mut out = String::new();
+    for (r, row) in cells.iter().enumerate() {
+        let is_header = header && r == 0;
+        let rendered: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, ((text, width), column))| {
+                let pad = column - width;
+                let (left, right) = match aligns.get(i).copied().unwrap_or(Align::Left) {
+                    Align::Left => (0, pad),
+                    Align::Right => (pad, 0),
+                    Align::Center => (pad / 2, pad - pad / 2),
+                };
+                let style = if is_header { BOLD } else { "" };
+                format!(
+                    "{}{}{}{}{}",
+                    " ".repeat(left),
+                    style,
+                    text,
+                    if is_header { RESET } else { "" },
+                    " ".repeat(right)
+                )
+            })
+            .collect();
+        out.push_str(&rendered.join(&format!(" {}│{} ", DIM, RESET)));
+        out.push('\n');
+        if is_header {
+            let rule: Vec<String> = widths.iter().map(|w| "─".repeat(*w)).collect();
+            out.push_str(&format!("{}{}{}\n", DIM, rule.join("─┼─"), RESET));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_all(chunks: &[&str]) -> String {
+        let mut renderer = MarkdownRenderer::new();
+        let mut out: String = chunks.iter().map(|c| renderer.push(c)).collect();
+        out.push_str(&renderer.finish());
+        out
+    }
+
+    /// Drops ANSI escape sequences, leaving the visible text.
+    fn visible(styled: &str) -> String {
+        let mut out = String::new();
+        let mut chars = styled.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    /// Renders `input` whole, character by character, and split in two at
+    /// every character boundary, checks each way gives the same output, and
+    /// returns it.
+    fn assert_renders_same(input: &str) -> String {
+        let whole = render_all(&[input]);
+        let chars: Vec<String> = input.chars().map(String::from).collect();
+        let chars: Vec<&str> = chars.iter().map(String::as_str).collect();
+        assert_eq!(render_all(&chars), whole, "char by char");
+        for (split, _) in input.char_indices() {
+            let (a, b) = input.split_at(split);
+            assert_eq!(render_all(&[a, b]), whole, "split at {}", split);
+        }
+        whole
+    }
+
+    #[test]
+    fn plain_text_passes_through() {
+        let input = "Hello, world.\nSecond line with 42 and a-b.\n";
+        assert_eq!(assert_renders_same(input), input);
+    }
+
+    #[test]
+    fn styles_closed_emphasis() {
+        let out = assert_renders_same("a *b* **c** ~~d~~ `e` f\n");
+        assert_eq!(visible(&out), "a b c d e f\n");
+        for style in [ITALIC, BOLD, STRIKE, CODE] {
+            assert!(out.contains(style), "{:?}", out);
+        }
+    }
+
+    #[test]
+    fn unclosed_markers_stay_literal() {
+        for input in [
+            "use *.rs files\n",
+            "**never closed\n",
+            "a `tick and *star\n",
+            "~~gone\n",
+            "*a `b* c\n",
+        ] {
+            let out = assert_renders_same(input);
+            assert_eq!(visible(&out), input, "{:?}", out);
+        }
+    }
+
+    #[test]
+    fn unclosed_outer_span_keeps_inner_spans() {
+        let out = assert_renders_same("**a `code` b\n");
+        assert_eq!(visible(&out), "**a code b\n");
+        assert!(out.contains(CODE));
+        assert!(!out.contains(BOLD), "{:?}", out);
+    }
+
+    #[test]
+    fn snake_case_is_not_emphasis() {
+        let out = assert_renders_same("call my_long_name now\n");
+        assert_eq!(out, "call my_long_name now\n");
+    }
+
+    #[test]
+    fn renders_block_prefixes() {
+        let out = assert_renders_same("# Title\n- item\n12. twelfth\n> quoted\n");
+        assert_eq!(visible(&out), "Title\n• item\n12. twelfth\n│ quoted\n");
+    }
+
+    #[test]
+    fn renders_rules() {
+        let out = assert_renders_same("---\n");
+        assert_eq!(visible(&out), format!("{}\n", "─".repeat(RULE_WIDTH)));
+    }
+
+    #[test]
+    fn highlights_fenced_code_without_changing_it() {
+        let input = "```rust\nfn main() { let x = *y; }\n```\nafter\n";
+        let out = assert_renders_same(input);
+        assert_eq!(visible(&out), input);
+        assert!(out.contains("\x1b[38;2;"), "{:?}", out);
+    }
+
+    #[test]
+    fn aligns_table_columns() {
+        let out = assert_renders_same("| a | long |\n|---|---:|\n| bbb | c |\n");
+        assert_eq!(visible(&out), "a   │ long\n────┼─────\nbbb │    c\n");
+    }
+
+    #[test]
+    fn finish_flushes_an_unterminated_line() {
+        let mut renderer = MarkdownRenderer::new();
+        let mut out = renderer.push("| x |\nend *open");
+        out.push_str(&renderer.finish());
+        assert_eq!(visible(&out), "x\nend *open\n");
+    }
+}
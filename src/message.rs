@@ -0,0 +1,208 @@
+//! Chat messages in the OpenAI wire format.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+// ------ Domain Types ------
+
+/// Who a message is from.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Instructions that frame the whole conversation.
+    System,
+    /// The person asking.
+    User,
+    /// The model.
+    Assistant,
+    /// The output of a tool call, answering the assistant's request.
+    Tool,
+}
+
+impl Role {
+    /// The role's name on the wire, e.g. `assistant`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "system" => Ok(Role::System),
+            "user" => Ok(Role::User),
+            "assistant" => Ok(Role::Assistant),
+            "tool" => Ok(Role::Tool),
+            other => Err(format!(
+                "unknown role `{}` (expected system, user, assistant or tool)",
+                other
+            )),
+        }
+    }
+}
+
+/// The `type` tag of a content part.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentType {
+    /// `text`
+    Text,
+    /// `image_url`
+    ImageUrl,
+}
+
+/// A text part of a message.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TextContent {
+    /// Always [`ContentType::Text`].
+    #[serde(rename = "type")]
+    pub content_type: ContentType,
+    /// The text itself.
+    pub text: String,
+}
+
+/// Where an image part's image comes from.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImageUrl {
+    /// An `http(s)` URL or a `data:` URL with the encoded image.
+    pub url: String,
+}
+
+/// An image part of a message.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImageContent {
+    /// Always [`ContentType::ImageUrl`].
+    #[serde(rename = "type")]
+    pub content_type: ContentType,
+    /// The image.
+    pub image_url: ImageUrl,
+}
+
+/// One part of a message: text or an image.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum ContentPart {
+    /// A text part.
+    Text(TextContent),
+    /// An image part.
+    Image(ImageContent),
+}
+
+impl ContentPart {
+    /// A text part.
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text(TextContent {
+            content_type: ContentType::Text,
+            text: text.into(),
+        })
+    }
+}
+
+/// A function call requested by the model.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ToolCall {
+    /// Chosen by the server; the tool's answer refers back to it.
+    pub id: String,
+    /// Always `function` so far.
+    #[serde(rename = "type", default = "function_kind")]
+    pub kind: String,
+    /// The function to call and its arguments.
+    pub function: FunctionCall,
+}
+
+/// The function a [`ToolCall`] asks for.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FunctionCall {
+    /// The tool's name.
+    pub name: String,
+    /// JSON-encoded arguments object, exactly as the model produced it.
+    pub arguments: String,
+}
+
+pub(crate) fn function_kind() -> String {
+    "function".to_string()
+}
+
+/// One turn of a conversation.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChatMessage {
+    /// Who the message is from.
+    pub role: Role,
+    /// Text and image parts, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content: Vec<ContentPart>,
+    /// Tools an assistant message asks to have called.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// For a tool message, the call it answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    /// A message with these parts and no tool calls.
+    pub fn new(role: Role, content: Vec<ContentPart>) -> Self {
+        Self {
+            role,
+            content,
+            tool_calls: vec![],
+            tool_call_id: None,
+        }
+    }
+
+    /// A system prompt.
+    pub fn system(text: impl Into<String>) -> Self {
+        Self::new(Role::System, vec![ContentPart::text(text)])
+    }
+
+    /// A user turn with text and image parts.
+    pub fn user(content: Vec<ContentPart>) -> Self {
+        Self::new(Role::User, content)
+    }
+
+    /// A plain assistant answer.
+    pub fn assistant(text: impl Into<String>) -> Self {
+        Self::new(Role::Assistant, vec![ContentPart::text(text)])
+    }
+
+    /// An assistant turn that asks for tool calls, with whatever text came along.
+    pub fn assistant_tool_calls(text: String, tool_calls: Vec<ToolCall>) -> Self {
+        let content = if text.is_empty() {
+            vec![]
+        } else {
+            vec![ContentPart::text(text)]
+        };
+        Self {
+            tool_calls,
+            ..Self::new(Role::Assistant, content)
+        }
+    }
+
+    /// The output of the tool call with this id.
+    pub fn tool(tool_call_id: impl Into<String>, output: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new(Role::Tool, vec![ContentPart::text(output)])
+        }
+    }
+
+    /// The text parts of the message joined together, ignoring images.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text(t) => Some(t.text.as_str()),
+                ContentPart::Image(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,245 @@
+//! An in-process transport that replays scripted responses, for tests.
+
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, stream};
+use reqwest::Url;
+use serde_json::{Value, json};
+
+use crate::{Error, Result, request::ChatRequest, transport::ApiError, transport::LlmTransport};
+
+// ------ Scripted Responses ------
+
+#[derive(Clone, Debug)]
+enum Chunk {
+    Data(Bytes),
+    /// The connection breaks at this point.
+    Fail(String),
+}
+
+/// One scripted answer to a chat request: an HTTP error, or a body delivered
+/// in chunks that may end in a broken connection.
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    status: u16,
+    chunks: Vec<Chunk>,
+}
+
+impl MockResponse {
+    /// A successful response whose body arrives in exactly these chunks.
+    pub fn chunks<I, B>(chunks: I) -> Self
+    where
+        I: IntoIterator<Item = B>,
+        B: Into<Bytes>,
+    {
+        Self {
+            status: 200,
+            chunks: chunks.into_iter().map(|c| Chunk::Data(c.into())).collect(),
+        }
+    }
+
+    /// A non-streaming response with this JSON body.
+    pub fn json(body: Value) -> Self {
+        Self::chunks([body.to_string()])
+    }
+
+    /// A streamed response with one `data:` event per payload, one event per
+    /// chunk, followed by `data: [DONE]`.
+    pub fn sse<I, S>(payloads: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::chunks(
+            payloads
+                .into_iter()
+                .map(|p| format!("data: {}\n\n", p.as_ref()))
+                .chain(["data: [DONE]\n\n".to_string()]),
+        )
+    }
+
+    /// A streamed answer sending `deltas` as content, then `finish_reason: stop`.
+    pub fn text_stream(deltas: &[&str]) -> Self {
+        let chunks = deltas
+            .iter()
+            .map(|d| json!({ "choices": [{ "index": 0, "delta": { "content": d } }] }))
+            .chain([json!({ "choices": [{ "index": 0, "delta": {}, "finish_reason": "stop" }] })]);
+        Self::sse(chunks.map(|c| c.to_string()))
+    }
+
+    /// A non-streaming answer with this content.
+    pub fn text(content: &str) -> Self {
+        Self::json(json!({
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop"
+            }]
+        }))
+    }
+
+    /// The server rejects the request with this status and body.
+    pub fn error(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            chunks: vec![Chunk::Data(Bytes::copy_from_slice(body.as_bytes()))],
+        }
+    }
+
+    /// Drops the final `data: [DONE]` event, as if the server just closed the stream.
+    pub fn without_done(mut self) -> Self {
+        if let Some(Chunk::Data(last)) = self.chunks.last()
+            && last.as_ref() == b"data: [DONE]\n\n"
+        {
+            self.chunks.pop();
+        }
+        self
+    }
+
+    /// Breaks the connection after the first `count` chunks, failing with
+    /// [`Error::Transport`] like a real broken HTTP body.
+    pub fn fail_after(mut self, count: usize, message: &str) -> Self {
+        self.chunks.truncate(count);
+        self.chunks.push(Chunk::Fail(message.to_string()));
+        self
+    }
+
+    /// Re-cuts the body into chunks of random length (1 to 16 bytes), so
+    /// events, lines and UTF-8 sequences are split at arbitrary points. The
+    /// same `seed` always gives the same cuts; a connection failure stays last.
+    pub fn split_randomly(mut self, seed: u64) -> Self {
+        let failure = match self.chunks.last() {
+            Some(Chunk::Fail(_)) => self.chunks.pop(),
+            _ => None,
+        };
+        let body = self.body();
+        let mut rng = XorShift(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1);
+        let mut chunks = Vec::new();
+        let mut rest = body.as_slice();
+        while !rest.is_empty() {
+            let len = (1 + rng.next() % 16) as usize;
+            let (chunk, tail) = rest.split_at(len.min(rest.len()));
+            chunks.push(Chunk::Data(Bytes::copy_from_slice(chunk)));
+            rest = tail;
+        }
+        chunks.extend(failure);
+        self.chunks = chunks;
+        self
+    }
+
+    /// All body bytes, without the chunk boundaries.
+    fn body(&self) -> Vec<u8> {
+        self.chunks
+            .iter()
+            .flat_map(|c| match c {
+                Chunk::Data(bytes) => bytes.to_vec(),
+                Chunk::Fail(_) => vec![],
+            })
+            .collect()
+    }
+}
+
+/// Small deterministic generator so chunk splits are reproducible without extra dependencies.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+// ------ Mock Transport ------
+
+#[derive(Default)]
+struct State {
+    responses: VecDeque<MockResponse>,
+    requests: Vec<ChatRequest>,
+    models: Option<Value>,
+}
+
+/// Answers chat requests with queued [`MockResponse`]s, in order, and records
+/// every request it receives.
+///
+/// Clones share their state, so a test can keep one handle to inspect the
+/// requests after moving another into an [`LlmClient`](crate::LlmClient).
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockTransport {
+    /// A transport with no responses queued.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the answer to the next chat request.
+    pub fn reply(self, response: MockResponse) -> Self {
+        self.state.lock().unwrap().responses.push_back(response);
+        self
+    }
+
+    /// The JSON document returned for any GET, such as the model list.
+    pub fn models(self, body: Value) -> Self {
+        self.state.lock().unwrap().models = Some(body);
+        self
+    }
+
+    /// The chat requests received so far.
+    pub fn requests(&self) -> Vec<ChatRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// How many queued responses have not been used yet.
+    pub fn remaining(&self) -> usize {
+        self.state.lock().unwrap().responses.len()
+    }
+}
+
+#[async_trait]
+impl LlmTransport for MockTransport {
+    async fn send(
+        &self,
+        _endpoint: &Url,
+        request: &ChatRequest,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let response = {
+            let mut state = self.state.lock().unwrap();
+            state.requests.push(request.clone());
+            state.responses.pop_front()
+        };
+        let response =
+            response.ok_or_else(|| Error::protocol("mock transport has no response queued"))?;
+        if !(200..300).contains(&response.status) {
+            let body = String::from_utf8_lossy(&response.body()).into_owned();
+            return Err(ApiError::from_body(response.status, &body).into());
+        }
+        Ok(stream::iter(response.chunks.into_iter().map(
+            |chunk| match chunk {
+                Chunk::Data(bytes) => Ok(bytes),
+                Chunk::Fail(message) => Err(Error::transport(io::Error::new(
+                    io::ErrorKind::ConnectionReset,
+                    message,
+                ))),
+            },
+        )))
+    }
+
+    async fn get_json(&self, _url: &Url) -> Result<Value> {
+        self.state
+            .lock()
+            .unwrap()
+            .models
+            .clone()
+            .ok_or_else(|| Error::protocol("mock transport has no JSON document set"))
+    }
+}
@@ -0,0 +1,471 @@
+//! A fake OpenAI-compatible server for offline development, demos and
+//! end-to-end tests of [`HttpTransport`](crate::HttpTransport).
+
+use std::{
+    convert::Infallible,
+    fs, io,
+    net::{SocketAddr, TcpListener},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use hyper::{
+    Body, Method, Request, Response, StatusCode,
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::info;
+
+use crate::{Error, Result};
+
+/// Model reported when the script lists none.
+const DEFAULT_MODEL: &str = "mock";
+
+// ------ Script ------
+
+/// What the mock server answers, read from a TOML file:
+///
+/// ```toml
+/// models = ["mock-small", "mock-large"]
+/// token_delay_ms = 30
+/// replies = ["First canned answer.", "Second canned answer."]
+///
+/// [[rules]]
+/// match = "(?i)weather"
+/// reply = "Sunny, 21 °C."
+/// reasoning = "The user wants a forecast."
+///
+/// [[rules]]
+/// match = "overload"
+/// status = 503
+/// error = "server is busy"
+///
+/// [[rules]]
+/// match = "hang up"
+/// reply = "This answer never finishes"
+/// disconnect_after = 2
+/// ```
+///
+/// Rules are checked in order against the last user message. Without a
+/// matching rule the `replies` are used in turn; without replies the server
+/// echoes the message back.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Script {
+    /// Model ids listed by `/v1/models`.
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Pause before each streamed token, and per token before a non-streamed answer.
+    #[serde(default)]
+    pub token_delay_ms: u64,
+    /// Checked in order; the first match answers.
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Canned answers used in turn when no rule matches.
+    #[serde(default)]
+    pub replies: Vec<String>,
+}
+
+/// Answers messages matching a regular expression.
+///
+/// The answer fields are spelled out rather than flattened from [`Reply`], so a
+/// mistyped key is rejected instead of silently ignored.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// The regular expression, written `match` in the script.
+    #[serde(rename = "match")]
+    pub pattern: String,
+    /// The answer text; `{{prompt}}` is replaced by the user's message.
+    pub reply: Option<String>,
+    /// Streamed as `reasoning_content` before the answer.
+    pub reasoning: Option<String>,
+    /// Answer with the user's message itself.
+    #[serde(default)]
+    pub echo: bool,
+    /// Overrides the script's `token_delay_ms`.
+    pub token_delay_ms: Option<u64>,
+    /// Fail the request with this HTTP status instead of answering.
+    pub status: Option<u16>,
+    /// Error message sent with `status`.
+    pub error: Option<String>,
+    /// Break the connection after streaming this many tokens.
+    pub disconnect_after: Option<usize>,
+    /// Defaults to `stop`.
+    pub finish_reason: Option<String>,
+}
+
+impl Rule {
+    /// The answer this rule gives.
+    pub fn reply(&self) -> Reply {
+        Reply {
+            reply: self.reply.clone(),
+            reasoning: self.reasoning.clone(),
+            echo: self.echo,
+            token_delay_ms: self.token_delay_ms,
+            status: self.status,
+            error: self.error.clone(),
+            disconnect_after: self.disconnect_after,
+            finish_reason: self.finish_reason.clone(),
+        }
+    }
+}
+
+/// One scripted answer.
+#[derive(Debug, Clone, Default)]
+pub struct Reply {
+    /// The answer text; `{{prompt}}` is replaced by the user's message.
+    pub reply: Option<String>,
+    /// Streamed as `reasoning_content` before the answer.
+    pub reasoning: Option<String>,
+    /// Answer with the user's message itself.
+    pub echo: bool,
+    /// Overrides the script's `token_delay_ms`.
+    pub token_delay_ms: Option<u64>,
+    /// Fail the request with this HTTP status instead of answering.
+    pub status: Option<u16>,
+    /// Error message sent with `status`.
+    pub error: Option<String>,
+    /// Break the connection after streaming this many tokens.
+    pub disconnect_after: Option<usize>,
+    /// Defaults to `stop`.
+    pub finish_reason: Option<String>,
+}
+
+impl Script {
+    /// Reads a TOML script.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            Error::config(format!("could not read script {}: {}", path.display(), e))
+        })?;
+        toml::from_str(&text)
+            .map_err(|e| Error::config(format!("invalid script {}: {}", path.display(), e)))
+    }
+}
+
+// ------ Answers ------
+
+/// A script with its rules compiled, shared by all connections.
+pub struct MockServer {
+    script: Script,
+    rules: Vec<(Regex, Reply)>,
+    next_reply: AtomicUsize,
+}
+
+impl MockServer {
+    /// Compiles the script's rules; an invalid pattern is a config error.
+    pub fn new(script: Script) -> Result<Self> {
+        let rules = script
+            .rules
+            .iter()
+            .map(|rule| {
+                let pattern = Regex::new(&rule.pattern).map_err(|e| {
+                    Error::config(format!("invalid rule pattern `{}`: {}", rule.pattern, e))
+                })?;
+                Ok((pattern, rule.reply()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            script,
+            rules,
+            next_reply: AtomicUsize::new(0),
+        })
+    }
+
+    fn reply_for(&self, prompt: &str) -> Reply {
+        if let Some((_, reply)) = self.rules.iter().find(|(re, _)| re.is_match(prompt)) {
+            return reply.clone();
+        }
+        let replies = &self.script.replies;
+        if replies.is_empty() {
+            return Reply {
+                echo: true,
+                ..Reply::default()
+            };
+        }
+        let next = self.next_reply.fetch_add(1, Ordering::Relaxed);
+        Reply {
+            reply: Some(replies[next % replies.len()].clone()),
+            ..Reply::default()
+        }
+    }
+
+    fn models(&self) -> Value {
+        let ids = if self.script.models.is_empty() {
+            vec![DEFAULT_MODEL.to_string()]
+        } else {
+            self.script.models.clone()
+        };
+        json!({
+            "object": "list",
+            "data": ids
+                .iter()
+                .map(|id| json!({ "id": id, "object": "model", "owned_by": "mock-server" }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn complete(&self, body: &[u8]) -> Response<Body> {
+        let request: Value = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(e) => return error_response(400, &format!("invalid JSON body: {}", e)),
+        };
+        let Some(messages) = request.get("messages").and_then(Value::as_array) else {
+            return error_response(400, "`messages` is required");
+        };
+        let prompt = messages
+            .iter()
+            .rev()
+            .find(|m| m["role"] == "user")
+            .map(message_text)
+            .unwrap_or_default();
+        let reply = self.reply_for(&prompt);
+        if let Some(status) = reply.status {
+            let message = reply.error.as_deref().unwrap_or("injected error");
+            return error_response(status, message);
+        }
+
+        let text = if reply.echo {
+            prompt.clone()
+        } else {
+            reply
+                .reply
+                .as_deref()
+                .unwrap_or_default()
+                .replace("{{prompt}}", &prompt)
+        };
+        let answer = Answer {
+            model: request
+                .get("model")
+                .and_then(Value::as_str)
+                .or(self.script.models.first().map(String::as_str))
+                .unwrap_or(DEFAULT_MODEL)
+                .to_string(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            reasoning: tokens(reply.reasoning.as_deref().unwrap_or_default()),
+            content: tokens(&text),
+            prompt_tokens: messages
+                .iter()
+                .map(|m| tokens(&message_text(m)).len())
+                .sum(),
+            finish_reason: reply.finish_reason.unwrap_or_else(|| "stop".to_string()),
+            delay: Duration::from_millis(
+                reply.token_delay_ms.unwrap_or(self.script.token_delay_ms),
+            ),
+            disconnect_after: reply.disconnect_after,
+        };
+        if request["stream"].as_bool().unwrap_or(false) {
+            let include_usage = request["stream_options"]["include_usage"]
+                .as_bool()
+                .unwrap_or(false);
+            answer.stream(include_usage)
+        } else {
+            answer.whole()
+        }
+    }
+}
+
+/// The text of a message whose content is a string or a list of parts.
+fn message_text(message: &Value) -> String {
+    match &message["content"] {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| p["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Splits text into word-sized tokens, each carrying the whitespace after it,
+/// so that joining them gives the text back.
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut previous_space = true;
+    for c in text.chars() {
+        let space = c.is_whitespace();
+        match tokens.last_mut() {
+            Some(token) if space || !previous_space => token.push(c),
+            _ => tokens.push(c.to_string()),
+        }
+        previous_space = space;
+    }
+    tokens
+}
+
+/// A chosen answer, ready to be sent whole or streamed.
+struct Answer {
+    model: String,
+    created: u64,
+    reasoning: Vec<String>,
+    content: Vec<String>,
+    prompt_tokens: usize,
+    finish_reason: String,
+    delay: Duration,
+    disconnect_after: Option<usize>,
+}
+
+impl Answer {
+    fn usage(&self) -> Value {
+        let completion = self.reasoning.len() + self.content.len();
+        json!({
+            "prompt_tokens": self.prompt_tokens,
+            "completion_tokens": completion,
+            "total_tokens": self.prompt_tokens + completion,
+        })
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Option<&str>) -> Bytes {
+        let chunk = json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+        });
+        Bytes::from(format!("data: {}\n\n", chunk))
+    }
+
+    fn stream(self, include_usage: bool) -> Response<Body> {
+        let events = async_stream::stream! {
+            yield Ok(self.chunk(json!({ "role": "assistant" }), None));
+            let deltas = self
+                .reasoning
+                .iter()
+                .map(|t| json!({ "reasoning_content": t }))
+                .chain(self.content.iter().map(|t| json!({ "content": t })));
+            for (sent, delta) in deltas.enumerate() {
+                if self.disconnect_after == Some(sent) {
+                    // Let hyper flush the tokens already sent before the connection drops.
+                    tokio::task::yield_now().await;
+                    yield Err(io::Error::new(io::ErrorKind::ConnectionAborted, "injected disconnect"));
+                    return;
+                }
+                tokio::time::sleep(self.delay).await;
+                yield Ok(self.chunk(delta, None));
+            }
+            yield Ok(self.chunk(json!({}), Some(&self.finish_reason)));
+            if include_usage {
+                let usage = json!({
+                    "id": "chatcmpl-mock",
+                    "object": "chat.completion.chunk",
+                    "created": self.created,
+                    "model": self.model,
+                    "choices": [],
+                    "usage": self.usage(),
+                });
+                yield Ok(Bytes::from(format!("data: {}\n\n", usage)));
+            }
+            yield Ok(Bytes::from_static(b"data: [DONE]\n\n"));
+        };
+        Response::builder()
+            .header(CONTENT_TYPE, "text/event-stream")
+            .body(Body::wrap_stream(events))
+            .unwrap()
+    }
+
+    fn whole(self) -> Response<Body> {
+        let body = json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": self.content.concat(),
+                    "reasoning_content": (!self.reasoning.is_empty()).then(|| self.reasoning.concat()),
+                },
+                "finish_reason": self.finish_reason,
+            }],
+            "usage": self.usage(),
+        });
+        let delay = self.delay * (self.reasoning.len() + self.content.len()) as u32;
+        let body = async_stream::stream! {
+            tokio::time::sleep(delay).await;
+            yield Ok::<_, io::Error>(body.to_string());
+        };
+        Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::wrap_stream(body))
+            .unwrap()
+    }
+}
+
+fn json_response(status: u16, body: &Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// An error in the shape OpenAI uses, which [`ApiError`](crate::ApiError) understands.
+fn error_response(status: u16, message: &str) -> Response<Body> {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    json_response(
+        status.as_u16(),
+        &json!({
+            "error": {
+                "code": status.as_u16(),
+                "message": message,
+                "type": "mock_error",
+            }
+        }),
+    )
+}
+
+// ------ HTTP ------
+
+async fn handle(server: Arc<MockServer>, request: Request<Body>) -> Response<Body> {
+    let path = request.uri().path();
+    let path = path.strip_prefix("/v1").unwrap_or(path).to_string();
+    let method = request.method().clone();
+    info!(%method, %path, "Mock request");
+    match (method, path.as_str()) {
+        (Method::GET, "/models") => json_response(200, &server.models()),
+        (Method::POST, "/chat/completions") => match hyper::body::to_bytes(request).await {
+            Ok(body) => server.complete(&body),
+            Err(e) => error_response(400, &format!("could not read body: {}", e)),
+        },
+        _ => error_response(404, &format!("no route for {}", path)),
+    }
+}
+
+/// Binds to `addr`; port 0 picks a free port.
+pub fn bind(addr: SocketAddr) -> Result<TcpListener> {
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+/// Serves `server` on `listener` until the process ends.
+pub async fn serve(listener: TcpListener, server: MockServer) -> Result<()> {
+    let server = Arc::new(server);
+    let service = make_service_fn(move |_| {
+        let server = server.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let server = server.clone();
+                async move { Ok::<_, Infallible>(handle(server, request).await) }
+            }))
+        }
+    });
+    hyper::Server::from_tcp(listener)
+        .map_err(|e| Error::Io(io::Error::other(e)))?
+        .serve(service)
+        .await
+        .map_err(|e| Error::Io(io::Error::other(e)))
+}
@@ -0,0 +1,49 @@
+//! The server's model list.
+
+use reqwest::Url;
+use serde_json::Value;
+
+// ------ Models ------
+
+/// A model entry from `GET /v1/models`.
+#[derive(Clone, Debug)]
+pub struct ModelInfo {
+    /// The name to pass as `model`.
+    pub id: String,
+    /// Who provides the model, if the server says.
+    pub owned_by: Option<String>,
+    /// Context window in tokens, if the server reports it.
+    pub context_length: Option<u64>,
+}
+
+impl ModelInfo {
+    pub(crate) fn from_json(json: &Value) -> Option<Self> {
+        // Servers report the context window under different keys: vLLM uses
+        // `max_model_len`, llama.cpp nests `n_ctx_train` under `meta`.
+        let context_length = ["/max_model_len", "/context_length", "/meta/n_ctx_train"]
+            .iter()
+            .find_map(|p| json.pointer(p).and_then(Value::as_u64));
+        Some(Self {
+            id: json.get("id")?.as_str()?.to_string(),
+            owned_by: json
+                .get("owned_by")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            context_length,
+        })
+    }
+}
+
+/// Derives the `/v1/models` URL from a chat completions endpoint.
+pub(crate) fn models_url(endpoint: &Url) -> Url {
+    let path = endpoint.path().trim_end_matches('/');
+    let base = path.strip_suffix("/chat/completions").unwrap_or(path);
+    let mut url = endpoint.clone();
+    if base.ends_with("/v1") {
+        url.set_path(&format!("{}/models", base));
+    } else {
+        url.set_path(&format!("{}/v1/models", base));
+    }
+    url.set_query(None);
+    url
+}
@@ -0,0 +1,245 @@
+//! Writing responses to stdout in the chosen format as their events arrive.
+
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use futures::StreamExt;
+use tokio::io::{self, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    ChatEvent, ChatEvents, ChatResponse, Error, Result, markdown::MarkdownRenderer,
+    stats::StatusLine,
+};
+
+// ------ Output Formats ------
+
+/// How responses are written to stdout.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// The answer text as it streams in
+    #[default]
+    Text,
+    /// One JSON object with the answer, usage and timings once the response is complete
+    Json,
+    /// One JSON line per stream event
+    Ndjson,
+    /// The answer as a Markdown document, with reasoning in a collapsible block
+    Markdown,
+}
+
+/// Output settings shared by one-shot prompts and the interactive chat.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OutputOptions {
+    /// How responses are written to stdout.
+    pub format: Format,
+    /// Show progress and a statistics summary on stderr.
+    pub stats: bool,
+    /// Render Markdown in text output; only useful when stdout is a terminal.
+    pub render_markdown: bool,
+}
+
+// ------ Event Writer ------
+
+/// Writes a response to `out` in one format as its events arrive.
+pub struct EventWriter<W> {
+    format: Format,
+    out: W,
+    /// Text has been written since the last newline.
+    line_open: bool,
+    reasoning_open: bool,
+    markdown: Option<MarkdownRenderer>,
+}
+
+impl<W: AsyncWrite + Unpin> EventWriter<W> {
+    /// A writer for one response in `format`.
+    pub fn new(format: Format, out: W) -> Self {
+        Self {
+            format,
+            out,
+            line_open: false,
+            reasoning_open: false,
+            markdown: None,
+        }
+    }
+
+    /// Renders Markdown in the answer for the terminal. Only affects the text format.
+    pub fn render_markdown(mut self, render: bool) -> Self {
+        self.markdown = (render && self.format == Format::Text).then(MarkdownRenderer::new);
+        self
+    }
+
+    /// The format responses are written in.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Whether `event` produces output right away, rather than only at the end.
+    pub fn writes(&self, event: &ChatEvent) -> bool {
+        match self.format {
+            Format::Text => matches!(event, ChatEvent::ContentDelta(_)),
+            Format::Markdown => matches!(
+                event,
+                ChatEvent::ContentDelta(_)
+                    | ChatEvent::ReasoningDelta(_)
+                    | ChatEvent::ToolResult { .. }
+            ),
+            Format::Ndjson => true,
+            Format::Json => false,
+        }
+    }
+
+    /// Writes what this event adds to the output.
+    pub async fn event(&mut self, event: &ChatEvent) -> io::Result<()> {
+        match self.format {
+            Format::Text => self.text_event(event).await?,
+            Format::Markdown => self.markdown_event(event).await?,
+            Format::Ndjson => {
+                let mut line = serde_json::to_vec(event)?;
+                line.push(b'\n');
+                self.out.write_all(&line).await?;
+            }
+            Format::Json => return Ok(()),
+        }
+        self.out.flush().await
+    }
+
+    /// Writes whatever is only known once the response is complete.
+    pub async fn finish(&mut self, response: &ChatResponse) -> io::Result<()> {
+        if self.format == Format::Json {
+            let mut json = serde_json::to_vec_pretty(response)?;
+            json.push(b'\n');
+            self.out.write_all(&json).await?;
+            self.out.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn text_event(&mut self, event: &ChatEvent) -> io::Result<()> {
+        match event {
+            ChatEvent::ContentDelta(delta) => match &mut self.markdown {
+                Some(markdown) => {
+                    let rendered = markdown.push(delta);
+                    self.write(&rendered).await
+                }
+                None => self.write(delta).await,
+            },
+            ChatEvent::FinishReason(_) | ChatEvent::Done => {
+                if let Some(markdown) = &mut self.markdown {
+                    let rendered = markdown.finish();
+                    self.write(&rendered).await?;
+                }
+                self.end_line().await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn markdown_event(&mut self, event: &ChatEvent) -> io::Result<()> {
+        match event {
+            ChatEvent::ReasoningDelta(delta) => {
+                if !self.reasoning_open {
+                    self.out
+                        .write_all(b"<details>\n<summary>Reasoning</summary>\n\n")
+                        .await?;
+                    self.reasoning_open = true;
+                }
+                self.write(delta).await
+            }
+            ChatEvent::ContentDelta(delta) => {
+                self.close_reasoning().await?;
+                self.write(delta).await
+            }
+            ChatEvent::ToolResult { name, .. } => {
+                self.close_reasoning().await?;
+                self.end_line().await?;
+                self.write(&format!("> Called tool `{}`\n\n", name)).await
+            }
+            ChatEvent::FinishReason(_) | ChatEvent::Done => {
+                self.close_reasoning().await?;
+                self.end_line().await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn write(&mut self, text: &str) -> io::Result<()> {
+        if !text.is_empty() {
+            self.out.write_all(text.as_bytes()).await?;
+            self.line_open = !text.ends_with('\n');
+        }
+        Ok(())
+    }
+
+    async fn end_line(&mut self) -> io::Result<()> {
+        if self.line_open {
+            self.out.write_all(b"\n").await?;
+            self.line_open = false;
+        }
+        Ok(())
+    }
+
+    async fn close_reasoning(&mut self) -> io::Result<()> {
+        if self.reasoning_open {
+            self.end_line().await?;
+            self.out.write_all(b"\n</details>\n\n").await?;
+            self.reasoning_open = false;
+        }
+        Ok(())
+    }
+}
+
+// ------ Printing ------
+
+/// How often the live status line is redrawn.
+const STATUS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Passes events to `writer` as they arrive and collects the response,
+/// keeping `status` up to date meanwhile.
+pub async fn print_events<W: AsyncWrite + Unpin>(
+    mut events: ChatEvents<'_>,
+    writer: &mut EventWriter<W>,
+    status: &mut StatusLine,
+) -> Result<ChatResponse> {
+    let started = Instant::now();
+    let mut response = ChatResponse::default();
+    let mut ticker = tokio::time::interval(STATUS_INTERVAL);
+    // One listener for the whole answer, so a Ctrl-C during a slow write isn't lost.
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        let event = tokio::select! {
+            event = events.next() => event,
+            _ = ticker.tick(), if status.is_enabled() => {
+                status.draw(&response, started.elapsed());
+                continue;
+            }
+            _ = &mut ctrl_c => {
+                status.clear();
+                return Err(Error::Cancelled);
+            }
+        };
+        let Some(event) = event else {
+            break;
+        };
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                status.clear();
+                return Err(e);
+            }
+        };
+        response.record(&event, started.elapsed());
+        if writer.writes(&event) {
+            status.before_output();
+        }
+        tokio::select! {
+            result = writer.event(&event) => result?,
+            _ = &mut ctrl_c => {
+                status.clear();
+                return Err(Error::Cancelled);
+            }
+        }
+    }
+    status.clear();
+    Ok(response)
+}
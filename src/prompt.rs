@@ -0,0 +1,155 @@
+//! Assembling the prompt from flags, prompt file templates and piped stdin.
+
+use std::{fs, path::Path};
+
+use crate::{Error, Result};
+
+/// Placeholder that inlines piped stdin into a prompt template.
+const STDIN_VAR: &str = "stdin";
+
+// ------ Prompt ------
+
+/// A named block of input text sent alongside the instruction, such as piped stdin.
+#[derive(Clone, Debug)]
+pub struct Document {
+    /// Shown to the model as the document's name, e.g. `stdin`.
+    pub name: String,
+    /// The document's contents.
+    pub text: String,
+}
+
+/// The text of a user turn: what to do, plus the documents to do it on.
+#[derive(Clone, Debug, Default)]
+pub struct Prompt {
+    /// What the model should do; `None` when the documents stand alone.
+    pub instruction: Option<String>,
+    /// Input sent after the instruction, each in its own part.
+    pub documents: Vec<Document>,
+}
+
+impl From<&str> for Prompt {
+    fn from(text: &str) -> Self {
+        Self {
+            instruction: Some(text.to_string()),
+            documents: vec![],
+        }
+    }
+}
+
+impl Prompt {
+    /// Combines the prompt sources given on the command line.
+    ///
+    /// `instruction` comes from `--prompt` or `--prompt-file` and may contain
+    /// `{{name}}` placeholders filled from `vars`. Piped `stdin` fills a
+    /// `{{stdin}}` placeholder if there is one, is attached as a document if
+    /// there is an instruction, and otherwise becomes the instruction itself,
+    /// rendered with `vars` the same way.
+    pub fn resolve(
+        instruction: Option<String>,
+        vars: &[(String, String)],
+        stdin: Option<String>,
+    ) -> Result<Self> {
+        let stdin = stdin.filter(|s| !s.trim().is_empty());
+        let mut documents = vec![];
+        let instruction = match (instruction, stdin) {
+            (Some(template), stdin) => {
+                let uses_stdin = template.contains(&placeholder(STDIN_VAR));
+                let mut vars = vars.to_vec();
+                match stdin {
+                    Some(text) if uses_stdin => vars.push((STDIN_VAR.to_string(), text)),
+                    Some(text) => documents.push(Document {
+                        name: STDIN_VAR.to_string(),
+                        text,
+                    }),
+                    None => {}
+                }
+                // Only treat the text as a template when asked to, so literal braces survive.
+                if vars.is_empty() {
+                    Some(template)
+                } else {
+                    Some(render_template(&template, &vars)?)
+                }
+            }
+            (None, Some(text)) if !vars.is_empty() => Some(render_template(text.trim_end(), vars)?),
+            (None, stdin) => stdin.map(|s| s.trim_end().to_string()),
+        };
+        let prompt = Self {
+            instruction,
+            documents,
+        };
+        if prompt.instruction.is_none() {
+            return Err(Error::config(
+                "no prompt given: use --prompt, --prompt-file or pipe text on stdin",
+            ));
+        }
+        Ok(prompt)
+    }
+
+    /// Reads a prompt file; a missing file is a config error.
+    pub fn read_file(path: &Path) -> Result<String> {
+        fs::read_to_string(path).map_err(|e| {
+            Error::config(format!(
+                "could not read prompt file {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// The prompt as clearly separated text blocks: instruction first, then each document.
+    pub fn text_parts(&self) -> Vec<String> {
+        self.instruction
+            .iter()
+            .cloned()
+            .chain(self.documents.iter().map(|d| {
+                format!(
+                    "<document name=\"{}\">\n{}\n</document>",
+                    d.name,
+                    d.text.trim_end()
+                )
+            }))
+            .collect()
+    }
+}
+
+// ------ Templates ------
+
+fn placeholder(name: &str) -> String {
+    format!("{{{{{}}}}}", name)
+}
+
+/// Replaces `{{name}}` placeholders; any placeholder without a value is an error.
+fn render_template(template: &str, vars: &[(String, String)]) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + len].trim();
+        let value = vars
+            .iter()
+            .rev()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v)
+            .ok_or_else(|| {
+                Error::config(format!(
+                    "template variable `{}` has no value (use --var {}=...)",
+                    name, name
+                ))
+            })?;
+        out.push_str(&rest[..start]);
+        out.push_str(value);
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Parses a `--var NAME=VALUE` argument.
+pub fn parse_var(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got `{}`", s))?;
+    Ok((name.trim().to_string(), value.to_string()))
+}
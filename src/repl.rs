@@ -0,0 +1,197 @@
+use std::fs;
+
+use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::{
+    ChatMessage, ChatRequest, ImageEncoder, LlmClient, LlmTransport, Result, build_request_content,
+    cli::ask, output::OutputOptions, prompt::Prompt, session::Session,
+};
+
+const HELP: &str = "\
+Commands:
+  /help            Show this help
+  /reset           Forget the conversation so far (keeps the system prompt)
+  /image <path>    Attach an image (file, http(s) or data URL) to the next message
+  /system [text]   Set the system prompt (no text clears it)
+  /save <path>     Write the conversation to a JSON file
+  /exit            Leave the chat (Ctrl-D works too)";
+
+// ------ Interactive Chat ------
+
+/// Line-oriented chat loop that keeps every turn in a growing history.
+pub struct Repl<'a, T: LlmTransport> {
+    client: &'a LlmClient<T>,
+    encoder: &'a dyn ImageEncoder,
+    template: ChatRequest,
+    system: Option<String>,
+    history: Vec<ChatMessage>,
+    pending_images: Vec<String>,
+    session: Option<Session>,
+    output: OutputOptions,
+}
+
+enum Action {
+    Continue,
+    Exit,
+}
+
+impl<'a, T: LlmTransport> Repl<'a, T> {
+    pub fn new(
+        client: &'a LlmClient<T>,
+        encoder: &'a dyn ImageEncoder,
+        template: ChatRequest,
+        system: Option<String>,
+        history: Vec<ChatMessage>,
+        session: Option<Session>,
+        output: OutputOptions,
+    ) -> Self {
+        Self {
+            client,
+            encoder,
+            template,
+            system,
+            history,
+            pending_images: vec![],
+            session,
+            output,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        let mut lines = BufReader::new(io::stdin()).lines();
+        let mut stdout = io::stdout();
+        stdout
+            .write_all(b"Interactive chat. Type /help for commands.\n")
+            .await?;
+
+        loop {
+            stdout.write_all(b"> ").await?;
+            stdout.flush().await?;
+            // Ctrl-C cancels an answer in progress; at the prompt it leaves like Ctrl-D.
+            let line = tokio::select! {
+                line = lines.next_line() => line?,
+                _ = tokio::signal::ctrl_c() => None,
+            };
+            let Some(line) = line else {
+                stdout.write_all(b"\n").await?;
+                return Ok(());
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let action = if let Some(command) = line.strip_prefix('/') {
+                self.command(command).await?
+            } else {
+                self.send(line).await;
+                Action::Continue
+            };
+            if let Action::Exit = action {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn command(&mut self, input: &str) -> Result<Action> {
+        let (name, arg) = match input.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (input, ""),
+        };
+        let reply = match name {
+            "help" => HELP.to_string(),
+            "exit" | "quit" => return Ok(Action::Exit),
+            "reset" => {
+                self.history.clear();
+                self.pending_images.clear();
+                self.persist();
+                "Conversation cleared.".to_string()
+            }
+            "image" if arg.is_empty() => "Usage: /image <path>".to_string(),
+            "image" => {
+                self.pending_images.push(arg.to_string());
+                format!(
+                    "Image {} will be sent with the next message ({} attached).",
+                    arg,
+                    self.pending_images.len()
+                )
+            }
+            "system" if arg.is_empty() => {
+                self.system = None;
+                self.persist();
+                "System prompt cleared.".to_string()
+            }
+            "system" => {
+                self.system = Some(arg.to_string());
+                self.persist();
+                "System prompt set.".to_string()
+            }
+            "save" if arg.is_empty() => "Usage: /save <path>".to_string(),
+            "save" => match self.save(arg) {
+                Ok(()) => format!("Saved {} messages to {}.", self.messages().len(), arg),
+                Err(e) => {
+                    eprintln!("Could not save conversation: {}", e);
+                    return Ok(Action::Continue);
+                }
+            },
+            other => format!("Unknown command /{}. Type /help for commands.", other),
+        };
+        let mut stdout = io::stdout();
+        stdout.write_all(format!("{}\n", reply).as_bytes()).await?;
+        stdout.flush().await?;
+        Ok(Action::Continue)
+    }
+
+    async fn send(&mut self, prompt: &str) {
+        let parts = match build_request_content(
+            &Prompt::from(prompt),
+            &self.pending_images,
+            self.encoder,
+        ) {
+            Ok(parts) => parts,
+            Err(e) => {
+                eprintln!("Could not build message: {}", e);
+                return;
+            }
+        };
+        self.pending_images.clear();
+        self.history.push(ChatMessage::user(parts));
+
+        let request = self.template.clone().with_messages(self.messages());
+        match ask(self.client, request, None, self.output).await {
+            Ok(reply) => {
+                self.history.push(ChatMessage::assistant(reply.content));
+                self.persist();
+            }
+            Err(e) => {
+                // Drop the unanswered turn so the next attempt starts clean.
+                self.history.pop();
+                eprintln!("LLM request failed: {}", e);
+            }
+        }
+    }
+
+    /// Writes the conversation back to the named session, if there is one.
+    fn persist(&self) {
+        if let Some(session) = &self.session
+            && let Err(e) = session.save(&self.messages())
+        {
+            eprintln!("Could not save session: {}", e);
+        }
+    }
+
+    fn save(&self, path: &str) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.messages()).map_err(io::Error::from)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// The full message list sent to the model: system prompt first, then history.
+    fn messages(&self) -> Vec<ChatMessage> {
+        self.system
+            .iter()
+            .map(ChatMessage::system)
+            .chain(self.history.iter().cloned())
+            .collect()
+    }
+}
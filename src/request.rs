@@ -0,0 +1,190 @@
+//! The chat completion request and its builder.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{message::ChatMessage, structured::ResponseFormat, tools::ToolSpec};
+
+// ------ Chat Request Builder ------
+
+/// Optional sampling controls; only the fields that are set are sent.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SamplingParams {
+    /// Sampling temperature; lower is more deterministic.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Nucleus sampling: keep the smallest token set with this cumulative probability.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Only sample from the K most likely tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Drop tokens less likely than this fraction of the most likely token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    /// Maximum number of tokens to generate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Sequences that end generation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Random seed for reproducible sampling; llama.cpp takes -1 for a random one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Penalty applied to recently repeated tokens (llama.cpp).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    /// Penalty for tokens that already appeared at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    /// Penalty proportional to how often a token already appeared.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+}
+
+/// A chat completion request, built up with chained calls:
+///
+/// ```
+/// use simple_llm_query::{ChatMessage, ChatRequest};
+///
+/// let request = ChatRequest::new()
+///     .model("gemma-3-12b-it")
+///     .stream(true)
+///     .message(ChatMessage::system("Be concise."))
+///     .temperature(0.2);
+/// assert_eq!(request.messages.len(), 1);
+/// ```
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ChatRequest {
+    /// The model to use, for servers that host several.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Ask for the answer as server-sent events.
+    pub stream: bool,
+    /// Set together with `stream`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+    /// The conversation so far, oldest first.
+    pub messages: Vec<ChatMessage>,
+    /// Constrains the answer to JSON.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Tools the model may call.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolSpec>,
+    /// Sent as top-level fields of the request.
+    #[serde(flatten)]
+    pub sampling: SamplingParams,
+}
+
+/// Extra options for streamed responses.
+#[derive(Serialize, Clone, Debug)]
+pub struct StreamOptions {
+    /// Ask for a final chunk with token usage.
+    pub include_usage: bool,
+}
+
+impl ChatRequest {
+    /// An empty, non-streaming request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The model to use, for servers that host several.
+    pub fn model(mut self, m: impl Into<String>) -> Self {
+        self.model = Some(m.into());
+        self
+    }
+
+    /// Streaming requests also ask for a final chunk with token usage.
+    pub fn stream(mut self, s: bool) -> Self {
+        self.stream = s;
+        self.stream_options = s.then_some(StreamOptions {
+            include_usage: true,
+        });
+        self
+    }
+
+    /// Replaces the conversation.
+    pub fn with_messages(mut self, msgs: Vec<ChatMessage>) -> Self {
+        self.messages = msgs;
+        self
+    }
+
+    /// Appends one message to the conversation.
+    pub fn message(mut self, msg: ChatMessage) -> Self {
+        self.messages.push(msg);
+        self
+    }
+
+    /// Constrains the answer to JSON, optionally matching a schema.
+    pub fn response_format(mut self, f: ResponseFormat) -> Self {
+        self.response_format = Some(f);
+        self
+    }
+
+    /// Replaces all sampling parameters at once.
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Sets [`SamplingParams::temperature`].
+    pub fn temperature(mut self, v: f32) -> Self {
+        self.sampling.temperature = Some(v);
+        self
+    }
+
+    /// Sets [`SamplingParams::top_p`].
+    pub fn top_p(mut self, v: f32) -> Self {
+        self.sampling.top_p = Some(v);
+        self
+    }
+
+    /// Sets [`SamplingParams::top_k`].
+    pub fn top_k(mut self, v: u32) -> Self {
+        self.sampling.top_k = Some(v);
+        self
+    }
+
+    /// Sets [`SamplingParams::min_p`].
+    pub fn min_p(mut self, v: f32) -> Self {
+        self.sampling.min_p = Some(v);
+        self
+    }
+
+    /// Sets [`SamplingParams::max_tokens`].
+    pub fn max_tokens(mut self, v: u32) -> Self {
+        self.sampling.max_tokens = Some(v);
+        self
+    }
+
+    /// Adds a [stop sequence](SamplingParams::stop).
+    pub fn stop(mut self, s: impl Into<String>) -> Self {
+        self.sampling.stop.push(s.into());
+        self
+    }
+
+    /// Sets [`SamplingParams::seed`].
+    pub fn seed(mut self, v: i64) -> Self {
+        self.sampling.seed = Some(v);
+        self
+    }
+
+    /// Sets [`SamplingParams::repeat_penalty`].
+    pub fn repeat_penalty(mut self, v: f32) -> Self {
+        self.sampling.repeat_penalty = Some(v);
+        self
+    }
+
+    /// Sets [`SamplingParams::presence_penalty`].
+    pub fn presence_penalty(mut self, v: f32) -> Self {
+        self.sampling.presence_penalty = Some(v);
+        self
+    }
+
+    /// Sets [`SamplingParams::frequency_penalty`].
+    pub fn frequency_penalty(mut self, v: f32) -> Self {
+        self.sampling.frequency_penalty = Some(v);
+        self
+    }
+}
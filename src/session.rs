@@ -0,0 +1,304 @@
+//! Named conversations saved on disk between runs.
+
+use std::{
+    collections::HashSet,
+    env,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{ChatMessage, ContentPart, Error, Result, Role};
+
+/// Overrides the directory sessions are stored in.
+const DATA_DIR_ENV: &str = "SIMPLE_LLM_QUERY_DATA_DIR";
+
+/// Prefix marking an image that was moved out of the session file into the blob store.
+const BLOB_PREFIX: &str = "sha256:";
+
+// ------ Session Store ------
+
+/// On-disk layout of a single session file.
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    messages: Vec<ChatMessage>,
+}
+
+/// Summary of a stored session, as shown by `sessions list`.
+pub struct SessionSummary {
+    /// The name passed to `--session`.
+    pub name: String,
+    /// How many messages the session holds.
+    pub messages: usize,
+}
+
+/// Stores sessions as `sessions/<name>.json` under the data directory.
+///
+/// Inline `data:` images are written once to `images/<sha256>` and referenced by
+/// hash, so a session that resends the same photo every turn stays small.
+/// Deleting a session removes the images no other session refers to.
+pub struct SessionStore {
+    root: PathBuf,
+}
+
+impl SessionStore {
+    /// A store rooted at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The store in the user data directory, or in `$SIMPLE_LLM_QUERY_DATA_DIR` if set.
+    pub fn open_default() -> Result<Self> {
+        let root = match env::var_os(DATA_DIR_ENV) {
+            Some(dir) => PathBuf::from(dir),
+            None => dirs::data_dir()
+                .ok_or_else(|| Error::config("could not determine the user data directory"))?
+                .join("simple-llm-query"),
+        };
+        Ok(Self { root })
+    }
+
+    fn sessions_dir(&self) -> PathBuf {
+        self.root.join("sessions")
+    }
+
+    fn images_dir(&self) -> PathBuf {
+        self.root.join("images")
+    }
+
+    fn session_path(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(Error::config(format!(
+                "invalid session name `{}` (use letters, digits, `-`, `_` and `.`)",
+                name
+            )));
+        }
+        Ok(self.sessions_dir().join(format!("{}.json", name)))
+    }
+
+    /// Paths of all session files.
+    fn session_files(&self) -> Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(self.sessions_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) == Some("json") {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+
+    /// All stored sessions, sorted by name.
+    pub fn list(&self) -> Result<Vec<SessionSummary>> {
+        let mut sessions = Vec::new();
+        for path in self.session_files()? {
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let file = read_session_file(&path, &fs::read(&path)?)?;
+            sessions.push(SessionSummary {
+                name: name.to_string(),
+                messages: file.messages.len(),
+            });
+        }
+        sessions.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(sessions)
+    }
+
+    /// Loads a session, returning an empty history if it does not exist yet.
+    pub fn load(&self, name: &str) -> Result<Vec<ChatMessage>> {
+        let path = self.session_path(name)?;
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut file = read_session_file(&path, &bytes)?;
+        for part in file.messages.iter_mut().flat_map(|m| m.content.iter_mut()) {
+            if let ContentPart::Image(image) = part
+                && let Some(hash) = image.image_url.url.strip_prefix(BLOB_PREFIX)
+            {
+                // Only a well-formed hash may name a file, or the session could point anywhere.
+                if !is_blob_hash(hash) {
+                    return Err(Error::config(format!(
+                        "invalid image reference in session file {}",
+                        path.display()
+                    )));
+                }
+                image.image_url.url = fs::read_to_string(self.images_dir().join(hash))?;
+            }
+        }
+        Ok(file.messages)
+    }
+
+    /// Replaces a session's history, creating the session if needed.
+    pub fn save(&self, name: &str, messages: &[ChatMessage]) -> Result<()> {
+        let path = self.session_path(name)?;
+        let mut messages = messages.to_vec();
+        for part in messages.iter_mut().flat_map(|m| m.content.iter_mut()) {
+            if let ContentPart::Image(image) = part
+                && image.image_url.url.starts_with("data:")
+            {
+                let hash = self.store_blob(&image.image_url.url)?;
+                image.image_url.url = format!("{}{}", BLOB_PREFIX, hash);
+            }
+        }
+        fs::create_dir_all(self.sessions_dir())?;
+        let json =
+            serde_json::to_string_pretty(&SessionFile { messages }).map_err(io::Error::from)?;
+        write_atomic(&path, json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Deletes a session; deleting one that does not exist is a config error.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let path = self.session_path(name)?;
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::config(format!("session `{}` does not exist", name)));
+            }
+            result => result?,
+        }
+        self.remove_unused_blobs()
+    }
+
+    /// Deletes stored images that no session refers to any more. Nothing is
+    /// deleted if a session file cannot be read, since its references are unknown.
+    fn remove_unused_blobs(&self) -> Result<()> {
+        let mut used = HashSet::new();
+        for path in self.session_files()? {
+            let Ok(file) = fs::read(&path)
+                .map_err(Error::from)
+                .and_then(|bytes| read_session_file(&path, &bytes))
+            else {
+                return Ok(());
+            };
+            for part in file.messages.iter().flat_map(|m| &m.content) {
+                if let ContentPart::Image(image) = part
+                    && let Some(hash) = image.image_url.url.strip_prefix(BLOB_PREFIX)
+                {
+                    used.insert(hash.to_string());
+                }
+            }
+        }
+        let entries = match fs::read_dir(self.images_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            if let Some(hash) = name.to_str()
+                && is_blob_hash(hash)
+                && !used.contains(hash)
+            {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a data URL to the blob store (if not already there) and returns its hash.
+    fn store_blob(&self, data_url: &str) -> Result<String> {
+        let hash = Sha256::digest(data_url.as_bytes())
+            .iter()
+            .fold(String::new(), |mut hex, b| {
+                let _ = write!(hex, "{:02x}", b);
+                hex
+            });
+        let dir = self.images_dir();
+        let path = dir.join(&hash);
+        if !path.exists() {
+            fs::create_dir_all(&dir)?;
+            write_atomic(&path, data_url.as_bytes())?;
+        }
+        Ok(hash)
+    }
+}
+
+/// Whether `s` is a hex SHA-256 digest as written by `store_blob`.
+fn is_blob_hash(s: &str) -> bool {
+    s.len() == 64 && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn read_session_file(path: &Path, bytes: &[u8]) -> Result<SessionFile> {
+    serde_json::from_slice(bytes)
+        .map_err(|e| Error::config(format!("invalid session file {}: {}", path.display(), e)))
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(tmp, path)
+}
+
+// ------ Session Handle ------
+
+/// A named session bound to a store; loaded once and saved after every reply.
+pub struct Session {
+    store: SessionStore,
+    name: String,
+}
+
+impl Session {
+    /// Opens a session in the default store.
+    pub fn open(name: &str) -> Result<Self> {
+        Ok(Self {
+            store: SessionStore::open_default()?,
+            name: name.to_string(),
+        })
+    }
+
+    /// The stored history; empty for a new session.
+    pub fn load(&self) -> Result<Vec<ChatMessage>> {
+        self.store.load(&self.name)
+    }
+
+    /// Replaces the stored history.
+    pub fn save(&self, messages: &[ChatMessage]) -> Result<()> {
+        self.store.save(&self.name, messages)
+    }
+}
+
+/// Splits a leading system message off a stored history.
+pub fn split_system(mut messages: Vec<ChatMessage>) -> (Option<String>, Vec<ChatMessage>) {
+    match messages.first() {
+        Some(first) if first.role == Role::System => {
+            let system = messages.remove(0).text();
+            (Some(system), messages)
+        }
+        _ => (None, messages),
+    }
+}
+
+/// Writes a session in a human-readable form.
+pub fn write_messages(out: &mut impl io::Write, messages: &[ChatMessage]) -> io::Result<()> {
+    for message in messages {
+        writeln!(out, "[{}]", message.role.as_str())?;
+        for part in &message.content {
+            match part {
+                ContentPart::Text(text) => writeln!(out, "{}", text.text)?,
+                ContentPart::Image(image) if image.image_url.url.starts_with("data:") => {
+                    writeln!(out, "<inline image>")?
+                }
+                ContentPart::Image(image) => writeln!(out, "<image {}>", image.image_url.url)?,
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,323 @@
+//! Incremental decoder for `text/event-stream` bodies, following the WHATWG
+//! HTML "server-sent events" parsing rules.
+
+use std::collections::VecDeque;
+
+use futures::{Stream, StreamExt, stream};
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+// ------ Events ------
+
+/// One dispatched server-sent event.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    /// The `event:` field, or `message` if the block had none.
+    pub event: String,
+    /// All `data:` lines of the block joined with `\n`.
+    pub data: String,
+    /// The last event ID seen on the stream so far, if any.
+    pub id: Option<String>,
+    /// The reconnection time in milliseconds from the latest valid `retry:` field.
+    pub retry: Option<u64>,
+}
+
+// ------ Decoder ------
+
+/// Turns arbitrary byte chunks into events; chunks may split lines, line
+/// endings and UTF-8 sequences anywhere.
+///
+/// Besides the standard fields, an `error:` line is read as the data of an
+/// `error` event: that is how llama.cpp reports a failure mid-stream.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    /// The previous chunk ended in `\r`, so a leading `\n` belongs to that line ending.
+    skip_lf: bool,
+    bom_checked: bool,
+    event_type: String,
+    data: String,
+    last_id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl SseDecoder {
+    /// A decoder at the start of a stream.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds the next chunk and returns the events it completed.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Event> {
+        self.buffer.extend_from_slice(chunk);
+        if !self.bom_checked {
+            if self.buffer.len() < BOM.len() && BOM.starts_with(&self.buffer) {
+                return vec![];
+            }
+            if self.buffer.starts_with(BOM) {
+                self.buffer.drain(..BOM.len());
+            }
+            self.bom_checked = true;
+        }
+
+        let mut events = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < self.buffer.len() {
+            match self.buffer[i] {
+                b'\n' if self.skip_lf && i == start => {
+                    self.skip_lf = false;
+                    start = i + 1;
+                }
+                b'\r' | b'\n' => {
+                    self.skip_lf = self.buffer[i] == b'\r';
+                    let line = self.buffer[start..i].to_vec();
+                    events.extend(self.process_line(&line));
+                    start = i + 1;
+                }
+                _ => self.skip_lf = false,
+            }
+            i += 1;
+        }
+        self.buffer.drain(..start);
+        events
+    }
+
+    fn process_line(&mut self, line: &[u8]) -> Option<Event> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line[0] == b':' {
+            return None;
+        }
+        let line = String::from_utf8_lossy(line);
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_ref(), ""),
+        };
+        match field {
+            "event" => self.event_type = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "error" => {
+                self.event_type = "error".to_string();
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry = value.parse().ok();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<Event> {
+        let event_type = std::mem::take(&mut self.event_type);
+        if self.data.is_empty() {
+            return None;
+        }
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+        Some(Event {
+            event: if event_type.is_empty() {
+                "message".to_string()
+            } else {
+                event_type
+            },
+            data,
+            id: self.last_id.clone(),
+            retry: self.retry,
+        })
+    }
+}
+
+// ------ Stream Adapter ------
+
+/// Adapts a stream of byte chunks into a stream of events. Transport errors are
+/// passed through; an unterminated event at the end of the stream is dropped.
+pub fn decode<S, B, E>(chunks: S) -> impl Stream<Item = Result<Event, E>>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+{
+    let state = (Box::pin(chunks), SseDecoder::new(), VecDeque::new());
+    stream::unfold(state, |(mut chunks, mut decoder, mut pending)| async move {
+        loop {
+            if let Some(event) = pending.pop_front() {
+                return Some((Ok(event), (chunks, decoder, pending)));
+            }
+            match chunks.next().await {
+                Some(Ok(chunk)) => pending.extend(decoder.feed(chunk.as_ref())),
+                Some(Err(e)) => return Some((Err(e), (chunks, decoder, pending))),
+                None => return None,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(data: &str) -> Event {
+        Event {
+            event: "message".to_string(),
+            data: data.to_string(),
+            id: None,
+            retry: None,
+        }
+    }
+
+    fn decode_all(chunks: &[&[u8]]) -> Vec<Event> {
+        let mut decoder = SseDecoder::new();
+        chunks.iter().flat_map(|c| decoder.feed(c)).collect()
+    }
+
+    /// Decodes `input` whole, byte by byte, and split in two at every offset,
+    /// and checks each way gives `expected`.
+    fn assert_decodes(input: &[u8], expected: &[Event]) {
+        assert_eq!(decode_all(&[input]), expected, "whole input");
+        let bytes: Vec<&[u8]> = input.chunks(1).collect();
+        assert_eq!(decode_all(&bytes), expected, "byte by byte");
+        for split in 0..=input.len() {
+            let (a, b) = input.split_at(split);
+            assert_eq!(decode_all(&[a, b]), expected, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn decodes_simple_events() {
+        assert_decodes(
+            b"data: one\n\ndata: two\n\n",
+            &[message("one"), message("two")],
+        );
+    }
+
+    #[test]
+    fn accepts_all_line_endings() {
+        let expected = [message("a"), message("b")];
+        assert_decodes(b"data: a\r\n\r\ndata: b\r\n\r\n", &expected);
+        assert_decodes(b"data: a\r\rdata: b\r\r", &expected);
+        assert_decodes(b"data: a\n\r\ndata: b\r\n\n", &expected);
+    }
+
+    #[test]
+    fn joins_multi_line_data() {
+        assert_decodes(
+            b"data: first\ndata: second\n\n",
+            &[message("first\nsecond")],
+        );
+        assert_decodes(b"data\ndata\n\n", &[message("\n")]);
+    }
+
+    #[test]
+    fn strips_only_one_leading_space() {
+        assert_decodes(
+            b"data:no space\n\ndata:  two spaces\n\n",
+            &[message("no space"), message(" two spaces")],
+        );
+    }
+
+    #[test]
+    fn ignores_comments_and_unknown_fields() {
+        assert_decodes(
+            b": keep-alive\nfoo: bar\ndata: x\n: more\n\n",
+            &[message("x")],
+        );
+    }
+
+    #[test]
+    fn error_lines_become_error_events() {
+        assert_decodes(
+            b"data: a\n\nerror: {\"code\":500}\n\n",
+            &[
+                message("a"),
+                Event {
+                    event: "error".to_string(),
+                    data: "{\"code\":500}".to_string(),
+                    id: None,
+                    retry: None,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn blocks_without_data_are_not_dispatched() {
+        assert_decodes(b"event: ping\n\ndata: x\n\n", &[message("x")]);
+    }
+
+    #[test]
+    fn tracks_event_type_and_last_id() {
+        let expected = [
+            Event {
+                event: "update".to_string(),
+                data: "1".to_string(),
+                id: Some("7".to_string()),
+                retry: None,
+            },
+            Event {
+                event: "message".to_string(),
+                data: "2".to_string(),
+                id: Some("7".to_string()),
+                retry: None,
+            },
+        ];
+        assert_decodes(b"event: update\nid: 7\ndata: 1\n\ndata: 2\n\n", &expected);
+    }
+
+    #[test]
+    fn ignores_ids_containing_nul() {
+        assert_decodes(b"id: a\0b\ndata: x\n\n", &[message("x")]);
+    }
+
+    #[test]
+    fn parses_retry() {
+        let events = decode_all(&[b"retry: 1500\n\nretry: soon\ndata: x\n\n"]);
+        assert_eq!(events[0].retry, Some(1500));
+    }
+
+    #[test]
+    fn strips_leading_bom_once() {
+        assert_decodes(b"\xEF\xBB\xBFdata: x\n\n", &[message("x")]);
+        // A BOM later on is part of the field name, so the line is ignored.
+        assert_decodes(b"data: x\n\n\xEF\xBB\xBFdata: y\n\n", &[message("x")]);
+    }
+
+    #[test]
+    fn keeps_multibyte_characters_split_across_chunks() {
+        assert_decodes(
+            "data: héllo → wörld\n\n".as_bytes(),
+            &[message("héllo → wörld")],
+        );
+    }
+
+    #[test]
+    fn drops_unterminated_event_at_end_of_stream() {
+        assert_decodes(b"data: done\n\ndata: partial\n", &[message("done")]);
+    }
+
+    #[test]
+    fn decodes_openai_style_stream() {
+        let input = b"data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\ndata: [DONE]\n\n";
+        assert_decodes(
+            input,
+            &[
+                message("{\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}"),
+                message("[DONE]"),
+            ],
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_adapter_yields_events_and_errors() {
+        let chunks: Vec<Result<&[u8], &str>> =
+            vec![Ok(b"data: a\n"), Ok(b"\ndata: b\n\n"), Err("boom")];
+        let items: Vec<_> = decode(stream::iter(chunks)).collect().await;
+        assert_eq!(items, vec![Ok(message("a")), Ok(message("b")), Err("boom")]);
+    }
+}
@@ -0,0 +1,170 @@
+//! Token counts and speeds, and the progress line shown on stderr.
+
+use std::{
+    fmt,
+    io::{self, IsTerminal, Write},
+    time::Duration,
+};
+
+use crate::ChatResponse;
+
+// ------ Summary ------
+
+/// Throughput numbers for one response. Server-reported values are used when
+/// available; anything measured or estimated on the client is marked with `~`.
+#[derive(Clone, Debug)]
+pub struct Stats {
+    prompt_tokens: Option<u64>,
+    completion_tokens: u64,
+    completion_estimated: bool,
+    time_to_first_token: Option<Duration>,
+    tokens_per_second: Option<f64>,
+    rate_estimated: bool,
+    total: Duration,
+}
+
+impl From<&ChatResponse> for Stats {
+    fn from(response: &ChatResponse) -> Self {
+        let usage = response.usage.as_ref();
+        let timings = response.timings.as_ref();
+
+        let server_completion = usage
+            .map(|u| u.completion_tokens)
+            .or(timings.map(|t| t.predicted_n));
+        let completion_tokens = server_completion.unwrap_or_else(|| estimate_tokens(response));
+
+        let server_rate = timings
+            .filter(|t| t.predicted_ms > 0.0)
+            .map(|t| t.predicted_n as f64 * 1000.0 / t.predicted_ms);
+        // A non-streamed answer arrives all at once, so only the total time is meaningful.
+        let generating = match response.time_to_first_token {
+            Some(ttft) if response.deltas > 1 => response.latency.saturating_sub(ttft),
+            _ => response.latency,
+        };
+        let generating = Some(generating).filter(|d| !d.is_zero());
+        let client_rate = generating.map(|d| completion_tokens as f64 / d.as_secs_f64());
+
+        Self {
+            prompt_tokens: usage
+                .map(|u| u.prompt_tokens)
+                .or(timings.map(|t| t.prompt_n)),
+            completion_tokens,
+            completion_estimated: server_completion.is_none(),
+            time_to_first_token: response.time_to_first_token,
+            tokens_per_second: server_rate.or(client_rate),
+            rate_estimated: server_rate.is_none(),
+            total: response.latency,
+        }
+    }
+}
+
+/// Streaming servers send about one delta per token; otherwise assume four
+/// characters per token.
+fn estimate_tokens(response: &ChatResponse) -> u64 {
+    if response.deltas > 1 {
+        return response.deltas;
+    }
+    let chars = response.content.chars().count() + response.reasoning.chars().count();
+    chars.div_ceil(4) as u64
+}
+
+fn approx(estimated: bool) -> &'static str {
+    if estimated { "~" } else { "" }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.prompt_tokens {
+            Some(n) => write!(f, "prompt {} tok", n)?,
+            None => f.write_str("prompt ? tok")?,
+        }
+        write!(
+            f,
+            " | completion {}{} tok",
+            approx(self.completion_estimated),
+            self.completion_tokens
+        )?;
+        if let Some(ttft) = self.time_to_first_token {
+            write!(f, " | TTFT {:.2}s", ttft.as_secs_f64())?;
+        }
+        if let Some(rate) = self.tokens_per_second {
+            write!(f, " | {}{:.1} tok/s", approx(self.rate_estimated), rate)?;
+        }
+        write!(f, " | total {:.2}s", self.total.as_secs_f64())
+    }
+}
+
+// ------ Live Status Line ------
+
+/// A single self-overwriting line on stderr showing progress while a response streams.
+///
+/// When stdout is the same terminal, the line would collide with the answer, so
+/// it is only shown until the first answer text arrives.
+pub struct StatusLine {
+    enabled: bool,
+    until_output: bool,
+    shown: bool,
+}
+
+impl StatusLine {
+    /// A status line that is active only if `enabled` and stderr is a terminal.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: enabled && io::stderr().is_terminal(),
+            until_output: io::stdout().is_terminal(),
+            shown: false,
+        }
+    }
+
+    /// Whether the status line is shown at all.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Redraws the line for a response that has been running for `elapsed`.
+    pub fn draw(&mut self, response: &ChatResponse, elapsed: Duration) {
+        if !self.enabled {
+            return;
+        }
+        let text = match response.time_to_first_token {
+            None => format!("waiting for first token… {:.1}s", elapsed.as_secs_f64()),
+            Some(ttft) => {
+                let tokens = estimate_tokens(response);
+                let generating = elapsed.saturating_sub(ttft).as_secs_f64();
+                let rate = if generating > 0.0 {
+                    tokens as f64 / generating
+                } else {
+                    0.0
+                };
+                format!(
+                    "~{} tok | ~{:.1} tok/s | {:.1}s",
+                    tokens,
+                    rate,
+                    elapsed.as_secs_f64()
+                )
+            }
+        };
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K{}", text);
+        let _ = stderr.flush();
+        self.shown = true;
+    }
+
+    /// Called before answer text is written to stdout.
+    pub fn before_output(&mut self) {
+        if self.until_output {
+            self.clear();
+            self.enabled = false;
+        }
+    }
+
+    /// Erases the line if it is currently shown.
+    pub fn clear(&mut self) {
+        if self.shown {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[2K");
+            let _ = stderr.flush();
+            self.shown = false;
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! JSON answers: requesting them and validating them against a schema.
+
+use std::{fmt, fs, path::Path};
+
+use jsonschema::Validator;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{Error, Result};
+
+// ------ Response Format ------
+
+/// The `response_format` field of a chat request.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Any JSON object.
+    JsonObject,
+    /// JSON matching a schema.
+    JsonSchema {
+        /// The schema and how strictly to follow it.
+        json_schema: JsonSchemaFormat,
+    },
+}
+
+/// The schema part of [`ResponseFormat::JsonSchema`].
+#[derive(Serialize, Clone, Debug)]
+pub struct JsonSchemaFormat {
+    name: String,
+    schema: Value,
+    strict: bool,
+}
+
+// ------ Structured Output ------
+
+/// Why a structured response was rejected.
+#[derive(Debug)]
+pub enum StructuredOutputError {
+    /// The model did not answer with valid JSON.
+    InvalidJson(serde_json::Error),
+    /// The JSON did not match the schema; one entry per violation.
+    SchemaViolation(Vec<String>),
+}
+
+impl fmt::Display for StructuredOutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidJson(e) => write!(f, "response is not valid JSON: {}", e),
+            Self::SchemaViolation(errors) => {
+                write!(
+                    f,
+                    "response does not match the schema: {}",
+                    errors.join("; ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for StructuredOutputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidJson(e) => Some(e),
+            Self::SchemaViolation(_) => None,
+        }
+    }
+}
+
+/// Requests JSON from the model and checks the answer locally before it is printed.
+pub struct StructuredOutput {
+    schema: Option<(Value, Validator)>,
+    strict: bool,
+    compact: bool,
+}
+
+impl StructuredOutput {
+    /// Any JSON object, without a schema.
+    pub fn json(compact: bool) -> Self {
+        Self {
+            schema: None,
+            strict: false,
+            compact,
+        }
+    }
+
+    /// JSON matching the JSON Schema in this file.
+    pub fn from_schema_file(path: &Path, compact: bool) -> Result<Self> {
+        let invalid = |e: &dyn fmt::Display| {
+            Error::config(format!("invalid JSON schema {}: {}", path.display(), e))
+        };
+        let text = fs::read(path).map_err(|e| {
+            Error::config(format!("could not read schema {}: {}", path.display(), e))
+        })?;
+        let schema: Value = serde_json::from_slice(&text).map_err(|e| invalid(&e))?;
+        let validator = jsonschema::validator_for(&schema).map_err(|e| invalid(&e))?;
+        Ok(Self {
+            schema: Some((schema, validator)),
+            strict: false,
+            compact,
+        })
+    }
+
+    /// Asks the server to enforce the schema strictly while generating. OpenAI
+    /// then requires `additionalProperties: false` and every property listed
+    /// as required, and rejects other schemas.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// The `response_format` to send with the request.
+    pub fn response_format(&self) -> ResponseFormat {
+        match &self.schema {
+            Some((schema, _)) => ResponseFormat::JsonSchema {
+                json_schema: JsonSchemaFormat {
+                    name: "response".to_string(),
+                    schema: schema.clone(),
+                    strict: self.strict,
+                },
+            },
+            None => ResponseFormat::JsonObject,
+        }
+    }
+
+    /// Parses the response text and validates it against the schema, if any.
+    pub fn parse(&self, text: &str) -> Result<Value, StructuredOutputError> {
+        let value: Value =
+            serde_json::from_str(text.trim()).map_err(StructuredOutputError::InvalidJson)?;
+        if let Some((_, validator)) = &self.schema {
+            let errors: Vec<String> = validator
+                .iter_errors(&value)
+                .map(|e| {
+                    let path = e.instance_path().to_string();
+                    let path = if path.is_empty() {
+                        "/".to_string()
+                    } else {
+                        path
+                    };
+                    format!("{} at `{}`", e, path)
+                })
+                .collect();
+            if !errors.is_empty() {
+                return Err(StructuredOutputError::SchemaViolation(errors));
+            }
+        }
+        Ok(value)
+    }
+
+    /// Formats a parsed answer for printing.
+    pub fn render(&self, value: &Value) -> String {
+        if self.compact {
+            value.to_string()
+        } else {
+            serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+        }
+    }
+}
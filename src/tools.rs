@@ -0,0 +1,324 @@
+//! Functions the model may call: the built-in file tools and external commands.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{Error, Result, ToolCall};
+
+/// Tool output longer than this is cut off before it is sent back to the model.
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// How long an external command may run unless its declaration says otherwise.
+const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 60;
+
+// ------ Tool Trait ------
+
+/// A function the model may ask us to run locally.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    /// The name the model calls the tool by.
+    fn name(&self) -> &str;
+    /// Tells the model what the tool does and when to use it.
+    fn description(&self) -> &str;
+    /// JSON Schema describing the arguments object.
+    fn parameters(&self) -> Value;
+    /// Runs the tool; the result, or the error, is sent back to the model.
+    async fn call(&self, arguments: Value) -> Result<String>;
+}
+
+/// A tool as advertised in the `tools` field of a chat request.
+#[derive(Serialize, Clone, Debug)]
+pub struct ToolSpec {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: FunctionSpec,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct FunctionSpec {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+// ------ Registry ------
+
+/// The tools a client offers the model, by name.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: Vec<Box<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    /// A registry without tools.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a tool, replacing any with the same name.
+    pub fn register(&mut self, tool: Box<dyn Tool>) {
+        self.tools.retain(|t| t.name() != tool.name());
+        self.tools.push(tool);
+    }
+
+    /// Whether no tools are registered.
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// The tools as advertised in a request.
+    pub fn specs(&self) -> Vec<ToolSpec> {
+        self.tools
+            .iter()
+            .map(|t| ToolSpec {
+                kind: "function",
+                function: FunctionSpec {
+                    name: t.name().to_string(),
+                    description: t.description().to_string(),
+                    parameters: t.parameters(),
+                },
+            })
+            .collect()
+    }
+
+    /// Runs a requested tool call. Failures are returned as text so the model can react to them.
+    pub async fn call(&self, call: &ToolCall) -> String {
+        let Some(tool) = self.tools.iter().find(|t| t.name() == call.function.name) else {
+            return format!("error: unknown tool `{}`", call.function.name);
+        };
+        let arguments = if call.function.arguments.trim().is_empty() {
+            json!({})
+        } else {
+            match serde_json::from_str(&call.function.arguments) {
+                Ok(arguments) => arguments,
+                Err(e) => return format!("error: arguments are not valid JSON: {}", e),
+            }
+        };
+        match tool.call(arguments).await {
+            Ok(mut output) => {
+                if output.len() > MAX_OUTPUT_BYTES {
+                    let mut end = MAX_OUTPUT_BYTES;
+                    while !output.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    output.truncate(end);
+                    output.push_str("\n[output truncated]");
+                }
+                output
+            }
+            Err(e) => format!("error: {}", e),
+        }
+    }
+}
+
+// ------ Built-in Tools ------
+
+/// Names accepted by `--tool`.
+pub const BUILTIN_TOOLS: &[&str] = &["read_file", "list_directory"];
+
+/// A built-in tool that only reaches files under `root`, which must be canonical.
+pub fn builtin(name: &str, root: &Path) -> Option<Box<dyn Tool>> {
+    let root = root.to_path_buf();
+    match name {
+        "read_file" => Some(Box::new(ReadFile { root })),
+        "list_directory" => Some(Box::new(ListDirectory { root })),
+        _ => None,
+    }
+}
+
+fn path_argument(arguments: &Value) -> Result<&str> {
+    arguments
+        .get("path")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::tool("missing string argument `path`"))
+}
+
+/// Resolves a path the model asked for against `root`, following `..` and
+/// symlinks, and refuses it unless it stays under `root`.
+async fn resolve_in(root: &Path, requested: &str) -> Result<PathBuf> {
+    let path = tokio::fs::canonicalize(root.join(requested)).await?;
+    if !path.starts_with(root) {
+        return Err(Error::tool(format!(
+            "`{}` is outside the tool root {}",
+            requested,
+            root.display()
+        )));
+    }
+    Ok(path)
+}
+
+struct ReadFile {
+    root: PathBuf,
+}
+
+#[async_trait]
+impl Tool for ReadFile {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        "Read a UTF-8 text file from the local file system."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "Path of the file to read" }
+            },
+            "required": ["path"]
+        })
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        let path = resolve_in(&self.root, path_argument(&arguments)?).await?;
+        Ok(tokio::fs::read_to_string(path).await?)
+    }
+}
+
+struct ListDirectory {
+    root: PathBuf,
+}
+
+#[async_trait]
+impl Tool for ListDirectory {
+    fn name(&self) -> &str {
+        "list_directory"
+    }
+
+    fn description(&self) -> &str {
+        "List the entries of a local directory. Directories end with `/`."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "Directory to list" }
+            },
+            "required": ["path"]
+        })
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        let path = resolve_in(&self.root, path_argument(&arguments)?).await?;
+        let mut entries = tokio::fs::read_dir(path).await?;
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let mut name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type().await?.is_dir() {
+                name.push('/');
+            }
+            names.push(name);
+        }
+        names.sort();
+        Ok(names.join("\n"))
+    }
+}
+
+// ------ External Command Tools ------
+
+/// A tool backed by an external program, declared in a tools file.
+///
+/// The arguments object is written to the program's stdin as JSON and its
+/// stdout becomes the tool result. A program still running after
+/// `timeout_secs` (default 60) is killed.
+#[derive(Deserialize)]
+pub struct CommandTool {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default = "empty_object_schema")]
+    parameters: Value,
+    command: Vec<String>,
+    #[serde(default = "default_timeout_secs")]
+    timeout_secs: u64,
+}
+
+fn empty_object_schema() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_COMMAND_TIMEOUT_SECS
+}
+
+impl CommandTool {
+    /// Loads a JSON array of command tool declarations.
+    pub fn load_file(path: &Path) -> Result<Vec<Self>> {
+        let text = fs::read(path).map_err(|e| {
+            Error::config(format!(
+                "could not read tools file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let tools: Vec<Self> = serde_json::from_slice(&text)
+            .map_err(|e| Error::config(format!("invalid tools file {}: {}", path.display(), e)))?;
+        if let Some(tool) = tools.iter().find(|t| t.command.is_empty()) {
+            return Err(Error::config(format!(
+                "tool `{}` has an empty command",
+                tool.name
+            )));
+        }
+        Ok(tools)
+    }
+}
+
+#[async_trait]
+impl Tool for CommandTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> Value {
+        self.parameters.clone()
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        let mut child = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let run = async {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(arguments.to_string().as_bytes()).await?;
+            }
+            child.wait_with_output().await
+        };
+        let output = tokio::time::timeout(Duration::from_secs(self.timeout_secs), run)
+            .await
+            .map_err(|_| {
+                Error::tool(format!(
+                    "`{}` did not finish within {} s",
+                    self.command[0], self.timeout_secs
+                ))
+            })??;
+        if !output.status.success() {
+            return Err(Error::tool(format!(
+                "`{}` exited with {}: {}",
+                self.command[0],
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
@@ -0,0 +1,160 @@
+//! Sending requests to the server.
+
+use std::fmt;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, TryStreamExt};
+use reqwest::{Client, Url};
+use serde_json::Value;
+
+use crate::{Error, Result, auth::Auth, request::ChatRequest};
+
+// ------ Transport Trait ------
+
+/// Moves requests and raw response bodies between the client and a server.
+///
+/// [`HttpTransport`] is the real implementation; tests can substitute one that
+/// replays canned responses.
+#[async_trait]
+pub trait LlmTransport {
+    /// Posts a chat request and returns the response body as it arrives.
+    async fn send(
+        &self,
+        endpoint: &Url,
+        request: &ChatRequest,
+    ) -> Result<impl Stream<Item = Result<Bytes>>>;
+
+    /// Fetches and parses a JSON document, such as the model list.
+    async fn get_json(&self, url: &Url) -> Result<Value>;
+}
+
+/// Talks to an OpenAI-compatible server over HTTP.
+pub struct HttpTransport {
+    client: Client,
+}
+
+impl Default for HttpTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpTransport {
+    /// A transport without credentials.
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+        }
+    }
+
+    /// A transport that attaches the credentials and extra headers to every request.
+    pub fn with_auth(auth: &Auth) -> Result<Self> {
+        let client = Client::builder()
+            .default_headers(auth.header_map()?)
+            .build()?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl LlmTransport for HttpTransport {
+    async fn send(
+        &self,
+        endpoint: &Url,
+        request: &ChatRequest,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let resp = self
+            .client
+            .post(endpoint.clone())
+            .json(request)
+            .send()
+            .await?;
+        Ok(check_status(resp)
+            .await?
+            .bytes_stream()
+            .map_err(Error::from))
+    }
+
+    async fn get_json(&self, url: &Url) -> Result<Value> {
+        let resp = self.client.get(url.clone()).send().await?;
+        Ok(check_status(resp).await?.json().await?)
+    }
+}
+
+/// Turns a non-2xx response into an `ApiError` instead of handing its body to the parser.
+async fn check_status(resp: reqwest::Response) -> Result<reqwest::Response, ApiError> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body = resp.text().await.unwrap_or_default();
+    Err(ApiError::from_body(status.as_u16(), &body))
+}
+
+// ------ API Errors ------
+
+/// An error response from the server, e.g. `{"error": {"code": 404, "message": "..."}}`.
+#[derive(Debug)]
+pub struct ApiError {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The `code` field; llama.cpp sends a number, OpenAI a string like `invalid_api_key`.
+    pub code: Option<String>,
+    /// The `type` field, e.g. `invalid_request_error`.
+    pub kind: Option<String>,
+    /// What went wrong, as the server put it.
+    pub message: String,
+}
+
+impl ApiError {
+    pub(crate) fn from_body(status: u16, body: &str) -> Self {
+        let json: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+        // OpenAI nests the details under `error`; some servers send a bare string
+        // there, FastAPI-based ones (vLLM) use `detail`.
+        let error = json.get("error").unwrap_or(&json);
+        let field = |name: &str| match error.get(name) {
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(Value::Number(n)) => Some(n.to_string()),
+            _ => None,
+        };
+        let message = field("message")
+            .or_else(|| error.as_str().map(str::to_string))
+            .or_else(|| {
+                json.get("detail")
+                    .map(|d| d.as_str().map_or_else(|| d.to_string(), str::to_string))
+            })
+            .unwrap_or_else(|| {
+                let text = body.trim();
+                if text.is_empty() {
+                    reqwest::StatusCode::from_u16(status)
+                        .ok()
+                        .and_then(|s| s.canonical_reason())
+                        .unwrap_or("request failed")
+                        .to_string()
+                } else {
+                    text.chars().take(500).collect()
+                }
+            });
+        Self {
+            status,
+            code: field("code"),
+            kind: field("type"),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "server returned HTTP {}: {}", self.status, self.message)?;
+        match (&self.kind, &self.code) {
+            (Some(kind), Some(code)) => write!(f, " ({}, code {})", kind, code),
+            (Some(kind), None) => write!(f, " ({})", kind),
+            (None, Some(code)) => write!(f, " (code {})", code),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
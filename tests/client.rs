@@ -0,0 +1,353 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::{Value, json};
+use simple_llm_query::{
+    ChatEvent, ChatMessage, ChatRequest, ContentPart, Error, LlmClient, Result,
+    mock::{MockResponse, MockTransport},
+    tools::{Tool, ToolRegistry},
+};
+
+fn client(transport: &MockTransport) -> LlmClient<MockTransport> {
+    let endpoint = "http://mock/v1/chat/completions".parse().unwrap();
+    LlmClient::new(transport.clone(), endpoint)
+}
+
+fn request(stream: bool) -> ChatRequest {
+    ChatRequest::new()
+        .stream(stream)
+        .message(ChatMessage::user(vec![ContentPart::text("Hi")]))
+}
+
+/// A streamed answer with reasoning, non-ASCII content, usage and llama.cpp timings.
+fn rich_stream() -> MockResponse {
+    MockResponse::sse([
+        json!({ "choices": [{ "delta": { "reasoning_content": "Think" } }] }),
+        json!({ "choices": [{ "delta": { "reasoning_content": "ing." } }] }),
+        json!({ "choices": [{ "delta": { "content": "Héllo" } }] }),
+        json!({ "choices": [{ "delta": { "content": " → wörld" } }] }),
+        json!({ "choices": [{ "delta": {}, "finish_reason": "stop" }] }),
+        json!({
+            "choices": [],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 3 },
+            "timings": { "prompt_n": 5, "prompt_ms": 10.0, "predicted_n": 3, "predicted_ms": 30.0 }
+        }),
+    ]
+    .map(|c| c.to_string()))
+}
+
+// ------ Streaming ------
+
+#[tokio::test]
+async fn collects_a_streamed_answer() {
+    let transport = MockTransport::new().reply(rich_stream());
+    let response = client(&transport).chat(request(true)).await.unwrap();
+
+    assert_eq!(response.content, "Héllo → wörld");
+    assert_eq!(response.reasoning, "Thinking.");
+    assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+    let usage = response.usage.as_ref().unwrap();
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens), (5, 3));
+    assert_eq!(response.timings.as_ref().unwrap().predicted_n, 3);
+    assert!(response.time_to_first_token.is_some());
+    assert!(!response.truncated());
+}
+
+#[tokio::test]
+async fn chunk_boundaries_do_not_change_the_answer() {
+    for seed in 0..64 {
+        let transport = MockTransport::new().reply(rich_stream().split_randomly(seed));
+        let response = client(&transport).chat(request(true)).await.unwrap();
+        assert_eq!(response.content, "Héllo → wörld", "seed {}", seed);
+        assert_eq!(response.reasoning, "Thinking.", "seed {}", seed);
+        assert!(response.usage.is_some(), "seed {}", seed);
+    }
+}
+
+#[tokio::test]
+async fn stream_ends_with_done_after_the_deltas() {
+    let transport = MockTransport::new().reply(MockResponse::text_stream(&["a", "b"]));
+    let client = client(&transport);
+    let events: Vec<ChatEvent> = client
+        .chat_stream(request(true))
+        .map(|e| e.unwrap())
+        .collect()
+        .await;
+    let kinds: Vec<Value> = events
+        .iter()
+        .map(|e| serde_json::to_value(e).unwrap()["type"].clone())
+        .collect();
+    assert_eq!(
+        kinds,
+        ["content_delta", "content_delta", "finish_reason", "done"]
+    );
+}
+
+#[tokio::test]
+async fn missing_done_still_completes() {
+    let transport =
+        MockTransport::new().reply(MockResponse::text_stream(&["Hel", "lo"]).without_done());
+    let response = client(&transport).chat(request(true)).await.unwrap();
+    assert_eq!(response.content, "Hello");
+    assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+}
+
+#[tokio::test]
+async fn unterminated_last_event_is_dropped() {
+    let transport = MockTransport::new().reply(MockResponse::chunks([
+        "data: {\"choices\":[{\"delta\":{\"content\":\"kept\"}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"content\":\"lost\"}}]}\n",
+    ]));
+    let response = client(&transport).chat(request(true)).await.unwrap();
+    assert_eq!(response.content, "kept");
+    assert_eq!(response.finish_reason, None);
+}
+
+#[tokio::test]
+async fn sends_the_request_once_with_usage_reporting() {
+    let transport = MockTransport::new().reply(MockResponse::text_stream(&["ok"]));
+    client(&transport).chat(request(true)).await.unwrap();
+
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 1);
+    let body = serde_json::to_value(&requests[0]).unwrap();
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["stream_options"]["include_usage"], true);
+    assert_eq!(body["messages"][0]["content"][0]["text"], "Hi");
+}
+
+// ------ Non-streaming ------
+
+#[tokio::test]
+async fn collects_a_non_streamed_answer() {
+    let transport = MockTransport::new().reply(MockResponse::json(json!({
+        "choices": [{
+            "message": { "role": "assistant", "content": "Whole", "reasoning_content": "hmm" },
+            "finish_reason": "length"
+        }],
+        "usage": { "prompt_tokens": 2, "completion_tokens": 1 }
+    })));
+    let response = client(&transport).chat(request(false)).await.unwrap();
+    assert_eq!(response.content, "Whole");
+    assert_eq!(response.reasoning, "hmm");
+    assert!(response.truncated());
+    assert_eq!(response.usage.unwrap().completion_tokens, 1);
+}
+
+// ------ Errors ------
+
+#[tokio::test]
+async fn error_mid_stream_is_reported_after_earlier_events() {
+    let transport = MockTransport::new().reply(
+        MockResponse::text_stream(&["one", "two", "three"]).fail_after(2, "connection reset"),
+    );
+    let client = client(&transport);
+    let items: Vec<Result<ChatEvent>> = client.chat_stream(request(true)).collect().await;
+
+    let deltas: Vec<&str> = items
+        .iter()
+        .filter_map(|e| match e {
+            Ok(ChatEvent::ContentDelta(d)) => Some(d.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(deltas, ["one", "two"]);
+    assert!(matches!(items.last(), Some(Err(Error::Transport(_)))));
+}
+
+#[tokio::test]
+async fn error_mid_stream_survives_random_splits() {
+    for seed in 0..16 {
+        let transport = MockTransport::new().reply(
+            MockResponse::text_stream(&["one", "two"])
+                .fail_after(1, "boom")
+                .split_randomly(seed),
+        );
+        let err = client(&transport).chat(request(true)).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Transport(_)),
+            "seed {}: {:?}",
+            seed,
+            err
+        );
+    }
+}
+
+#[tokio::test]
+async fn malformed_event_is_a_protocol_error() {
+    let transport = MockTransport::new().reply(MockResponse::sse(["{\"choices\": ["]));
+    let err = client(&transport).chat(request(true)).await.unwrap_err();
+    assert!(matches!(err, Error::Protocol(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn http_errors_carry_the_status_and_message() {
+    let transport = MockTransport::new().reply(MockResponse::error(
+        401,
+        r#"{"error": {"message": "bad key", "type": "invalid_request_error"}}"#,
+    ));
+    let err = client(&transport).chat(request(true)).await.unwrap_err();
+    match err {
+        Error::Status(api) => {
+            assert_eq!(api.status, 401);
+            assert_eq!(api.message, "bad key");
+        }
+        other => panic!("expected a status error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn llama_cpp_error_lines_mid_stream_fail_the_request() {
+    let transport = MockTransport::new().reply(MockResponse::chunks([
+        "data: {\"choices\":[{\"delta\":{\"content\":\"partial\"}}]}\n\n",
+        "error: {\"code\":500,\"message\":\"context overflow\",\"type\":\"server_error\"}\n\n",
+    ]));
+    let err = client(&transport).chat(request(true)).await.unwrap_err();
+    match err {
+        Error::Status(api) => {
+            assert_eq!(api.status, 500);
+            assert_eq!(api.message, "context overflow");
+            assert_eq!(api.kind.as_deref(), Some("server_error"));
+        }
+        other => panic!("expected a status error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn error_payloads_mid_stream_fail_the_request() {
+    let transport = MockTransport::new().reply(MockResponse::sse([
+        json!({ "error": { "message": "upstream timed out", "type": "server_error", "code": null } })
+            .to_string(),
+    ]));
+    let err = client(&transport).chat(request(true)).await.unwrap_err();
+    match err {
+        Error::Status(api) => {
+            assert_eq!(api.status, 500);
+            assert_eq!(api.message, "upstream timed out");
+        }
+        other => panic!("expected a status error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn completion_without_choices_is_a_protocol_error() {
+    let transport = MockTransport::new().reply(MockResponse::json(json!({ "choices": [] })));
+    let err = client(&transport).chat(request(false)).await.unwrap_err();
+    assert!(matches!(err, Error::Protocol(_)), "{:?}", err);
+}
+
+// ------ Tool Calls ------
+
+struct Add;
+
+#[async_trait]
+impl Tool for Add {
+    fn name(&self) -> &str {
+        "add"
+    }
+
+    fn description(&self) -> &str {
+        "Adds two numbers."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({ "type": "object", "properties": { "a": {}, "b": {} } })
+    }
+
+    async fn call(&self, arguments: Value) -> Result<String> {
+        let sum = arguments["a"].as_i64().unwrap() + arguments["b"].as_i64().unwrap();
+        Ok(sum.to_string())
+    }
+}
+
+/// The model asks for `add(2, 3)` with its arguments spread over several deltas.
+fn tool_call_stream() -> MockResponse {
+    MockResponse::sse(
+        [
+            json!({ "choices": [{ "delta": { "tool_calls": [{
+                "index": 0, "id": "call_1", "type": "function",
+                "function": { "name": "add", "arguments": "" }
+            }] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{
+                "index": 0, "function": { "arguments": "{\"a\": 2," }
+            }] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{
+                "index": 0, "function": { "arguments": " \"b\": 3}" }
+            }] } }] }),
+            json!({ "choices": [{ "delta": {}, "finish_reason": "tool_calls" }] }),
+        ]
+        .map(|c| c.to_string()),
+    )
+}
+
+fn tool_client(transport: &MockTransport) -> LlmClient<MockTransport> {
+    let mut tools = ToolRegistry::new();
+    tools.register(Box::new(Add));
+    client(transport).with_tools(tools)
+}
+
+#[tokio::test]
+async fn runs_tool_calls_and_sends_the_results_back() {
+    for seed in [None, Some(1), Some(2), Some(3)] {
+        let first = match seed {
+            Some(seed) => tool_call_stream().split_randomly(seed),
+            None => tool_call_stream(),
+        };
+        let transport = MockTransport::new()
+            .reply(first)
+            .reply(MockResponse::text_stream(&["The sum is 5."]));
+        let client = tool_client(&transport);
+        let events: Vec<ChatEvent> = client
+            .chat_stream(request(true))
+            .map(|e| e.unwrap())
+            .collect()
+            .await;
+
+        assert!(events.iter().any(|e| matches!(
+            e,
+            ChatEvent::ToolResult { name, output, .. } if name == "add" && output == "5"
+        )));
+        assert!(matches!(events.last(), Some(ChatEvent::Done)));
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2, "seed {:?}", seed);
+        let second = serde_json::to_value(&requests[1]).unwrap();
+        assert_eq!(second["tools"][0]["function"]["name"], "add");
+        let messages = second["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        let call = &messages[1]["tool_calls"][0];
+        assert_eq!(call["id"], "call_1");
+        assert_eq!(call["function"]["arguments"], "{\"a\": 2, \"b\": 3}");
+        assert_eq!(messages[2]["role"], "tool");
+        assert_eq!(messages[2]["tool_call_id"], "call_1");
+    }
+}
+
+#[tokio::test]
+async fn gives_up_when_the_model_keeps_calling_tools() {
+    let mut transport = MockTransport::new();
+    for _ in 0..8 {
+        transport = transport.reply(tool_call_stream());
+    }
+    let err = tool_client(&transport)
+        .chat(request(true))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Tool(_)), "{:?}", err);
+    assert_eq!(transport.remaining(), 0);
+}
+
+// ------ Models ------
+
+#[tokio::test]
+async fn lists_models() {
+    let transport = MockTransport::new().models(json!({
+        "data": [
+            { "id": "qwen", "owned_by": "me", "meta": { "n_ctx_train": 32768 } },
+            { "id": "gemma" }
+        ]
+    }));
+    let models = client(&transport).models().await.unwrap();
+    let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(ids, ["qwen", "gemma"]);
+    assert_eq!(models[0].owned_by.as_deref(), Some("me"));
+}
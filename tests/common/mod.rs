@@ -0,0 +1,56 @@
+//! Fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{CommandFactory, FromArgMatches};
+use simple_llm_query::cli::Cli;
+
+/// A fresh directory named after the process and the test, so tests running
+/// in parallel never share files; removed again on drop.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(test: &str) -> Self {
+        let path =
+            env::temp_dir().join(format!("simple-llm-query-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self {
+            path: fs::canonicalize(path).unwrap(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `contents` to `name` inside the directory and returns its path.
+    pub fn write(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.path.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Parses a command line like the binary does, but without reading
+/// `SIMPLE_LLM_QUERY_*` variables, so the developer's own settings never leak in.
+pub fn parse_cli<'a>(args: impl IntoIterator<Item = &'a str>) -> Cli {
+    let command = Cli::command().mut_args(|arg| arg.env(None::<&str>));
+    let matches = command
+        .try_get_matches_from(std::iter::once("simple-llm-query").chain(args))
+        .unwrap();
+    Cli::from_arg_matches(&matches).unwrap()
+}
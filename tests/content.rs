@@ -0,0 +1,140 @@
+use std::{path::Path, sync::Mutex};
+
+use simple_llm_query::{
+    ContentPart, DataUrlEncoder, Error, ImageEncoder, Result, build_request_content,
+    prompt::{Document, Prompt},
+};
+
+/// Returns a fixed data URL per file and remembers which paths it was asked for.
+#[derive(Default)]
+struct RecordingEncoder {
+    paths: Mutex<Vec<String>>,
+}
+
+impl ImageEncoder for RecordingEncoder {
+    fn encode_path(&self, path: &Path) -> Result<String> {
+        let path = path.display().to_string();
+        self.paths.lock().unwrap().push(path.clone());
+        Ok(format!("data:image/png;base64,{}", path))
+    }
+}
+
+struct FailingEncoder;
+
+impl ImageEncoder for FailingEncoder {
+    fn encode_path(&self, path: &Path) -> Result<String> {
+        Err(Error::Image(format!("cannot encode {}", path.display())))
+    }
+}
+
+fn texts(parts: &[ContentPart]) -> Vec<&str> {
+    parts
+        .iter()
+        .filter_map(|p| match p {
+            ContentPart::Text(text) => Some(text.text.as_str()),
+            ContentPart::Image(_) => None,
+        })
+        .collect()
+}
+
+fn image_urls(parts: &[ContentPart]) -> Vec<&str> {
+    parts
+        .iter()
+        .filter_map(|p| match p {
+            ContentPart::Image(image) => Some(image.image_url.url.as_str()),
+            ContentPart::Text(_) => None,
+        })
+        .collect()
+}
+
+#[test]
+fn prompt_only_gives_one_text_part() {
+    let parts =
+        build_request_content(&Prompt::from("Hello"), &[], &RecordingEncoder::default()).unwrap();
+    assert_eq!(texts(&parts), ["Hello"]);
+    assert!(image_urls(&parts).is_empty());
+}
+
+#[test]
+fn documents_follow_the_instruction() {
+    let prompt = Prompt {
+        instruction: Some("Summarize".to_string()),
+        documents: vec![Document {
+            name: "stdin".to_string(),
+            text: "line one\nline two\n".to_string(),
+        }],
+    };
+    let parts = build_request_content(&prompt, &[], &RecordingEncoder::default()).unwrap();
+    assert_eq!(
+        texts(&parts),
+        [
+            "Summarize",
+            "<document name=\"stdin\">\nline one\nline two\n</document>"
+        ]
+    );
+}
+
+#[test]
+fn urls_pass_through_and_files_are_encoded_in_order() {
+    let encoder = RecordingEncoder::default();
+    let images = [
+        "a.png".to_string(),
+        "https://example.com/b.jpg".to_string(),
+        "data:image/gif;base64,R0lG".to_string(),
+        "c.webp".to_string(),
+    ];
+    let parts = build_request_content(&Prompt::from("Compare"), &images, &encoder).unwrap();
+
+    assert!(matches!(parts[0], ContentPart::Text(_)));
+    assert_eq!(
+        image_urls(&parts),
+        [
+            "data:image/png;base64,a.png",
+            "https://example.com/b.jpg",
+            "data:image/gif;base64,R0lG",
+            "data:image/png;base64,c.webp",
+        ]
+    );
+    assert_eq!(*encoder.paths.lock().unwrap(), ["a.png", "c.webp"]);
+}
+
+#[test]
+fn image_parts_serialize_as_image_url() {
+    let images = ["https://example.com/cat.png".to_string()];
+    let parts =
+        build_request_content(&Prompt::from("What is this?"), &images, &FailingEncoder).unwrap();
+    let json = serde_json::to_value(&parts).unwrap();
+    assert_eq!(json[0]["type"], "text");
+    assert_eq!(json[1]["type"], "image_url");
+    assert_eq!(json[1]["image_url"]["url"], "https://example.com/cat.png");
+}
+
+#[test]
+fn encoder_errors_are_passed_on() {
+    let images = ["missing.png".to_string()];
+    let err = build_request_content(&Prompt::from("Hi"), &images, &FailingEncoder).unwrap_err();
+    assert!(matches!(err, Error::Image(_)), "{:?}", err);
+}
+
+#[test]
+fn data_url_encoder_reads_real_images() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("food.jpeg");
+    let url = DataUrlEncoder.encode_path(&path).unwrap();
+    assert!(
+        url.starts_with("data:image/jpeg;base64,/9j/"),
+        "{}",
+        &url[..40]
+    );
+}
+
+#[test]
+fn data_url_encoder_rejects_other_files() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    let err = DataUrlEncoder.encode_path(&path).unwrap_err();
+    assert!(matches!(err, Error::Image(_)), "{:?}", err);
+
+    let err = DataUrlEncoder
+        .encode_path(Path::new("does/not/exist.png"))
+        .unwrap_err();
+    assert!(matches!(err, Error::Image(_)), "{:?}", err);
+}
@@ -0,0 +1,120 @@
+use std::{io::Cursor, path::Path};
+
+use image::{
+    DynamicImage, ImageFormat, RgbImage,
+    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+};
+use simple_llm_query::{
+    Error,
+    images::{self, ImageKind},
+};
+
+/// A 4x2 image, wider than it is tall.
+fn picture() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(4, 2, |x, _| {
+        image::Rgb([x as u8 * 60, 0, 0])
+    }))
+}
+
+fn jpeg() -> Vec<u8> {
+    let mut out = Vec::new();
+    picture()
+        .write_with_encoder(JpegEncoder::new(&mut out))
+        .unwrap();
+    out
+}
+
+fn png() -> Vec<u8> {
+    let mut out = Vec::new();
+    picture()
+        .write_with_encoder(PngEncoder::new(&mut out))
+        .unwrap();
+    out
+}
+
+/// An APP1 segment holding EXIF with just an orientation tag.
+fn exif_segment(orientation: u16) -> Vec<u8> {
+    let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+    exif.extend_from_slice(&orientation.to_be_bytes());
+    exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+    segment.extend_from_slice(&exif);
+    segment
+}
+
+/// Inserts `segment` right after the JPEG start-of-image marker.
+fn with_segment(jpeg: &[u8], segment: &[u8]) -> Vec<u8> {
+    [&jpeg[..2], segment, &jpeg[2..]].concat()
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// A PNG chunk with a valid CRC.
+fn png_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+    chunk.extend_from_slice(kind);
+    chunk.extend_from_slice(data);
+    chunk.extend_from_slice(&crc32(&chunk[4..]).to_be_bytes());
+    chunk
+}
+
+fn strip(kind: ImageKind, bytes: Vec<u8>) -> Vec<u8> {
+    images::strip_metadata(Path::new("photo"), kind, bytes).unwrap()
+}
+
+#[test]
+fn jpeg_metadata_is_removed_without_re_encoding() {
+    let original = jpeg();
+    let comment = [0xFF, 0xFE, 0x00, 0x07, b'h', b'o', b'm', b'e', b'!'];
+    let tagged = with_segment(&with_segment(&original, &exif_segment(1)), &comment);
+    assert_eq!(strip(ImageKind::Jpeg, tagged), original);
+}
+
+#[test]
+fn rotated_jpegs_are_re_encoded_upright() {
+    let stripped = strip(ImageKind::Jpeg, with_segment(&jpeg(), &exif_segment(6)));
+    assert!(!stripped.windows(4).any(|w| w == b"Exif"));
+    let img = image::load_from_memory_with_format(&stripped, ImageFormat::Jpeg).unwrap();
+    assert_eq!((img.width(), img.height()), (2, 4));
+}
+
+#[test]
+fn png_text_chunks_are_removed() {
+    let original = png();
+    // The IHDR chunk ends 33 bytes in; metadata goes right after it.
+    let text = png_chunk(b"tEXt", b"Location\0Home");
+    let tagged = [&original[..33], &text, &original[33..]].concat();
+    image::load_from_memory(&tagged).unwrap();
+    assert_eq!(strip(ImageKind::Png, tagged), original);
+}
+
+#[test]
+fn other_formats_are_left_alone() {
+    let mut gif = Vec::new();
+    picture()
+        .write_to(&mut Cursor::new(&mut gif), ImageFormat::Gif)
+        .unwrap();
+    assert_eq!(strip(ImageKind::Gif, gif.clone()), gif);
+}
+
+#[test]
+fn truncated_jpegs_are_image_errors() {
+    let jpeg = jpeg();
+    let err = images::strip_metadata(Path::new("photo"), ImageKind::Jpeg, jpeg[..10].to_vec())
+        .unwrap_err();
+    assert!(matches!(err, Error::Image(_)), "{:?}", err);
+}
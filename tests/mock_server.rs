@@ -0,0 +1,171 @@
+use std::net::SocketAddr;
+
+use futures::StreamExt;
+use simple_llm_query::{
+    ChatEvent, ChatMessage, ChatRequest, ContentPart, Error, HttpTransport, LlmClient,
+    mock_server::{self, MockServer, Script},
+};
+
+const SCRIPT: &str = r#"
+models = ["mock-small", "mock-large"]
+replies = ["First canned answer.", "Second canned answer."]
+
+[[rules]]
+match = "(?i)weather"
+reply = "Sunny. You asked: {{prompt}}"
+reasoning = "A forecast."
+
+[[rules]]
+match = "overload"
+status = 503
+error = "server is busy"
+
+[[rules]]
+match = "hang up"
+reply = "This answer never finishes at all"
+disconnect_after = 2
+
+[[rules]]
+match = "^echo"
+echo = true
+"#;
+
+/// Starts a mock server on a free port and returns a client pointed at it.
+async fn start(script: &str) -> LlmClient<HttpTransport> {
+    let script: Script = toml::from_str(script).unwrap();
+    let server = MockServer::new(script).unwrap();
+    let listener = mock_server::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(mock_server::serve(listener, server));
+    let endpoint = format!("http://{}/v1/chat/completions", addr)
+        .parse()
+        .unwrap();
+    LlmClient::new(HttpTransport::new(), endpoint)
+}
+
+fn request(prompt: &str, stream: bool) -> ChatRequest {
+    ChatRequest::new()
+        .stream(stream)
+        .message(ChatMessage::user(vec![ContentPart::text(prompt)]))
+}
+
+#[tokio::test]
+async fn rules_stream_reasoning_and_content() {
+    let client = start(SCRIPT).await;
+    let response = client
+        .chat(request("What is the weather?", true))
+        .await
+        .unwrap();
+    assert_eq!(response.content, "Sunny. You asked: What is the weather?");
+    assert_eq!(response.reasoning, "A forecast.");
+    assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+    assert!(response.usage.is_some());
+}
+
+#[tokio::test]
+async fn streams_one_delta_per_word() {
+    let client = start(SCRIPT).await;
+    let deltas: Vec<String> = client
+        .chat_stream(request("echo three words", true))
+        .filter_map(|e| async move {
+            match e.unwrap() {
+                ChatEvent::ContentDelta(d) => Some(d),
+                _ => None,
+            }
+        })
+        .collect()
+        .await;
+    assert_eq!(deltas, ["echo ", "three ", "words"]);
+}
+
+#[tokio::test]
+async fn canned_replies_are_used_in_turn() {
+    let client = start(SCRIPT).await;
+    let mut answers = Vec::new();
+    for stream in [true, false, true] {
+        let response = client.chat(request("Hi", stream)).await.unwrap();
+        answers.push(response.content);
+    }
+    assert_eq!(
+        answers,
+        [
+            "First canned answer.",
+            "Second canned answer.",
+            "First canned answer."
+        ]
+    );
+}
+
+#[tokio::test]
+async fn non_streamed_answers_carry_usage() {
+    let client = start(SCRIPT).await;
+    let response = client.chat(request("echo hello", false)).await.unwrap();
+    assert_eq!(response.content, "echo hello");
+    assert!(response.usage.unwrap().completion_tokens > 0);
+}
+
+#[tokio::test]
+async fn empty_script_echoes() {
+    let client = start("").await;
+    let response = client.chat(request("Same again", true)).await.unwrap();
+    assert_eq!(response.content, "Same again");
+}
+
+#[tokio::test]
+async fn scripted_status_is_an_api_error() {
+    let client = start(SCRIPT).await;
+    let err = client.chat(request("overload", true)).await.unwrap_err();
+    match err {
+        Error::Status(api) => {
+            assert_eq!(api.status, 503);
+            assert_eq!(api.message, "server is busy");
+        }
+        other => panic!("expected a status error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn disconnect_breaks_the_stream_after_some_tokens() {
+    let client = start(SCRIPT).await;
+    let items: Vec<_> = client
+        .chat_stream(request("please hang up", true))
+        .collect()
+        .await;
+    let deltas: Vec<&str> = items
+        .iter()
+        .filter_map(|e| match e {
+            Ok(ChatEvent::ContentDelta(d)) => Some(d.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(deltas, ["This ", "answer "]);
+    assert!(
+        matches!(items.last(), Some(Err(Error::Transport(_)))),
+        "{:?}",
+        items.last()
+    );
+}
+
+#[tokio::test]
+async fn lists_the_scripted_models() {
+    let client = start(SCRIPT).await;
+    let models = client.models().await.unwrap();
+    let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(ids, ["mock-small", "mock-large"]);
+}
+
+#[test]
+fn invalid_patterns_are_config_errors() {
+    let script: Script = toml::from_str("[[rules]]\nmatch = \"(\"\nreply = \"x\"\n").unwrap();
+    let err = MockServer::new(script).err().unwrap();
+    assert!(matches!(err, Error::Config(_)), "{:?}", err);
+}
+
+#[test]
+fn mistyped_rule_keys_are_rejected() {
+    for key in ["dissconnect_after = 2", "replay = \"x\""] {
+        let script = format!("[[rules]]\nmatch = \"x\"\n{}\n", key);
+        let err = toml::from_str::<Script>(&script).unwrap_err();
+        assert!(err.to_string().contains("unknown field"), "{}", err);
+    }
+}
@@ -0,0 +1,33 @@
+use simple_llm_query::{Error, prompt::Prompt};
+
+fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn stdin_prompts_are_rendered_with_vars() {
+    let stdin = Some("Translate into {{language}}.\n".to_string());
+    let prompt = Prompt::resolve(None, &vars(&[("language", "French")]), stdin).unwrap();
+    assert_eq!(
+        prompt.instruction.as_deref(),
+        Some("Translate into French.")
+    );
+    assert!(prompt.documents.is_empty());
+}
+
+#[test]
+fn stdin_prompts_without_vars_keep_their_braces() {
+    let stdin = Some("fn main() {{ }}".to_string());
+    let prompt = Prompt::resolve(None, &[], stdin).unwrap();
+    assert_eq!(prompt.instruction.as_deref(), Some("fn main() {{ }}"));
+}
+
+#[test]
+fn stdin_placeholders_without_a_value_are_errors() {
+    let stdin = Some("Say {{greeting}} in {{language}}".to_string());
+    let err = Prompt::resolve(None, &vars(&[("language", "French")]), stdin).unwrap_err();
+    assert!(matches!(err, Error::Config(_)), "{:?}", err);
+}
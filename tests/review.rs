@@ -0,0 +1,217 @@
+mod common;
+
+use std::{env, net::SocketAddr, path::PathBuf};
+
+use common::TempDir;
+use serde_json::Value;
+use simple_llm_query::{
+    Error,
+    cli::{self, Cli},
+    mock::{MockResponse, MockTransport},
+    mock_server::{self, MockServer, Script},
+};
+use tokio::process::Command;
+
+/// A directory holding a config file for one test, so the user's own config never leaks in.
+fn config(test: &str, contents: &str) -> TempDir {
+    let dir = TempDir::new(test);
+    dir.write("config.toml", contents);
+    dir
+}
+
+fn config_path(dir: &TempDir) -> PathBuf {
+    dir.path().join("config.toml")
+}
+
+fn cli(config: &TempDir, args: &[&str]) -> Cli {
+    let config = config_path(config);
+    let base = [
+        "--no-stdin",
+        "--output",
+        "ndjson",
+        "--llm-endpoint",
+        "http://mock/v1/chat/completions",
+        "--config",
+        config.to_str().unwrap(),
+    ];
+    common::parse_cli(base.iter().chain(args).copied())
+}
+
+/// The text parts of the last message of a recorded request.
+fn last_user_texts(request: &Value) -> Vec<String> {
+    let messages = request["messages"].as_array().unwrap();
+    let last = messages.last().unwrap();
+    assert_eq!(last["role"], "user");
+    last["content"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|p| p["type"] == "text")
+        .map(|p| p["text"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn requests(transport: &MockTransport) -> Vec<Value> {
+    transport
+        .requests()
+        .iter()
+        .map(|r| serde_json::to_value(r).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn review_sends_the_first_answer_back() {
+    let config = config("review", "");
+    let transport = MockTransport::new()
+        .reply(MockResponse::text_stream(&["First ", "draft"]))
+        .reply(MockResponse::text_stream(&["Revised"]));
+    let cli = cli(&config, &["--review", "-p", "Write a haiku"]);
+    cli::run_with_transport(cli, transport.clone())
+        .await
+        .unwrap();
+
+    let requests = requests(&transport);
+    assert_eq!(requests.len(), 2);
+    assert_eq!(last_user_texts(&requests[0]), ["Write a haiku"]);
+    let review = &last_user_texts(&requests[1])[0];
+    assert!(
+        review.contains("Original prompt: \"Write a haiku\""),
+        "{}",
+        review
+    );
+    assert!(
+        review.contains("First response: \"First draft\""),
+        "{}",
+        review
+    );
+    assert!(review.ends_with("Please review and revise."), "{}", review);
+    // The review replaces the first turn rather than extending the conversation.
+    assert_eq!(requests[1]["messages"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn without_review_only_one_request_is_sent() {
+    let config = config("no-review", "");
+    let transport = MockTransport::new().reply(MockResponse::text_stream(&["Done"]));
+    let cli = cli(&config, &["-p", "Hi"]);
+    cli::run_with_transport(cli, transport.clone())
+        .await
+        .unwrap();
+    assert_eq!(transport.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn review_uses_the_profile_prompt_and_keeps_system_and_images() {
+    let config = config(
+        "profile",
+        r#"
+        [profiles.checker]
+        review = true
+        review_prompt = "Check the facts."
+        system = "Be brief."
+        "#,
+    );
+    let transport = MockTransport::new()
+        .reply(MockResponse::text("It is blue."))
+        .reply(MockResponse::text("It is blue, mostly."));
+    let cli = cli(
+        &config,
+        &[
+            "--profile",
+            "checker",
+            "--no-stream",
+            "-p",
+            "What colour is the sky?",
+            "-i",
+            "https://example.com/sky.png",
+        ],
+    );
+    cli::run_with_transport(cli, transport.clone())
+        .await
+        .unwrap();
+
+    let requests = requests(&transport);
+    assert_eq!(requests.len(), 2);
+    for request in &requests {
+        assert_eq!(request["messages"][0]["role"], "system");
+        let user = request["messages"].as_array().unwrap().last().unwrap();
+        assert_eq!(
+            user["content"][1]["image_url"]["url"],
+            "https://example.com/sky.png"
+        );
+    }
+    let review = &last_user_texts(&requests[1])[0];
+    assert!(
+        review.contains("First response: \"It is blue.\""),
+        "{}",
+        review
+    );
+    assert!(review.ends_with("Check the facts."), "{}", review);
+}
+
+#[tokio::test]
+async fn no_review_flag_overrides_the_profile() {
+    let config = config("override", "[profiles.p]\nreview = true\n");
+    let transport = MockTransport::new().reply(MockResponse::text_stream(&["Once"]));
+    let cli = cli(&config, &["--profile", "p", "--no-review", "-p", "Hi"]);
+    cli::run_with_transport(cli, transport.clone())
+        .await
+        .unwrap();
+    assert_eq!(transport.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn failed_first_answer_skips_the_review() {
+    let config = config("failure", "");
+    let transport = MockTransport::new()
+        .reply(MockResponse::error(
+            500,
+            r#"{"error": {"message": "overloaded"}}"#,
+        ))
+        .reply(MockResponse::text_stream(&["unused"]));
+    let cli = cli(&config, &["--review", "-p", "Hi"]);
+    let err = cli::run_with_transport(cli, transport.clone())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, Error::Status(ref api) if api.status == 500),
+        "{:?}",
+        err
+    );
+    assert_eq!(transport.requests().len(), 1);
+    assert_eq!(transport.remaining(), 1);
+}
+
+#[tokio::test]
+async fn json_output_with_review_is_one_object() {
+    let config = config("json", "");
+    let script: Script = toml::from_str(r#"replies = ["First draft", "Revised"]"#).unwrap();
+    let listener = mock_server::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+    let endpoint = format!(
+        "http://{}/v1/chat/completions",
+        listener.local_addr().unwrap()
+    );
+    tokio::spawn(mock_server::serve(
+        listener,
+        MockServer::new(script).unwrap(),
+    ));
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_simple-llm-query"));
+    for (name, _) in env::vars_os() {
+        if name.to_string_lossy().starts_with("SIMPLE_LLM_QUERY_") {
+            command.env_remove(name);
+        }
+    }
+    let output = command
+        .args(["--no-stdin", "--output", "json", "--review", "-p", "Hi"])
+        .arg("--llm-endpoint")
+        .arg(&endpoint)
+        .arg("--config")
+        .arg(config_path(&config))
+        .output()
+        .await
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let response: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(response["content"], "Revised");
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+use serde_json::json;
+use simple_llm_query::{ChatMessage, ContentPart, Error, session::SessionStore};
+
+/// A fresh store in its own temporary directory.
+struct TempStore {
+    dir: TempDir,
+    store: SessionStore,
+}
+
+impl TempStore {
+    fn new(test: &str) -> Self {
+        let dir = TempDir::new(test);
+        Self {
+            store: SessionStore::new(dir.path()),
+            dir,
+        }
+    }
+
+    fn blobs(&self) -> usize {
+        fs::read_dir(self.dir.path().join("images")).map_or(0, |d| d.count())
+    }
+}
+
+fn image(url: &str) -> ContentPart {
+    serde_json::from_value(json!({ "type": "image_url", "image_url": { "url": url } })).unwrap()
+}
+
+fn with_image(url: &str) -> Vec<ChatMessage> {
+    vec![ChatMessage::user(vec![
+        ContentPart::text("Look"),
+        image(url),
+    ])]
+}
+
+fn image_url(messages: &[ChatMessage]) -> &str {
+    match &messages[0].content[1] {
+        ContentPart::Image(image) => &image.image_url.url,
+        ContentPart::Text(_) => panic!("expected an image part"),
+    }
+}
+
+#[test]
+fn inline_images_are_stored_once_and_restored() {
+    let temp = TempStore::new("roundtrip");
+    let messages = with_image("data:image/png;base64,AAAA");
+    temp.store.save("a", &messages).unwrap();
+    temp.store.save("b", &messages).unwrap();
+    assert_eq!(temp.blobs(), 1);
+
+    let loaded = temp.store.load("a").unwrap();
+    assert_eq!(image_url(&loaded), "data:image/png;base64,AAAA");
+    let file = fs::read_to_string(temp.dir.path().join("sessions/a.json")).unwrap();
+    assert!(file.contains("sha256:"), "{}", file);
+}
+
+#[test]
+fn image_references_must_be_hashes() {
+    let temp = TempStore::new("traversal");
+    fs::create_dir_all(temp.dir.path().join("sessions")).unwrap();
+    let session = json!({ "messages": with_image("sha256:../../.ssh/id_rsa") });
+    fs::write(
+        temp.dir.path().join("sessions/evil.json"),
+        session.to_string(),
+    )
+    .unwrap();
+
+    let err = temp.store.load("evil").unwrap_err();
+    assert!(matches!(err, Error::Config(_)), "{:?}", err);
+}
+
+#[test]
+fn deleting_a_session_removes_only_its_unused_images() {
+    let temp = TempStore::new("delete");
+    temp.store
+        .save("shared-1", &with_image("data:image/png;base64,AAAA"))
+        .unwrap();
+    temp.store
+        .save("shared-2", &with_image("data:image/png;base64,AAAA"))
+        .unwrap();
+    temp.store
+        .save("own", &with_image("data:image/png;base64,BBBB"))
+        .unwrap();
+    assert_eq!(temp.blobs(), 2);
+
+    temp.store.delete("own").unwrap();
+    assert_eq!(temp.blobs(), 1);
+    temp.store.delete("shared-1").unwrap();
+    assert_eq!(temp.blobs(), 1);
+    assert_eq!(
+        image_url(&temp.store.load("shared-2").unwrap()),
+        "data:image/png;base64,AAAA"
+    );
+    temp.store.delete("shared-2").unwrap();
+    assert_eq!(temp.blobs(), 0);
+}
+
+#[test]
+fn deleting_a_missing_session_is_an_error() {
+    let temp = TempStore::new("missing");
+    let err = temp.store.delete("nope").unwrap_err();
+    assert!(matches!(err, Error::Config(_)), "{:?}", err);
+}
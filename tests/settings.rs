@@ -0,0 +1,73 @@
+mod common;
+
+use common::TempDir;
+use serde_json::Value;
+use simple_llm_query::{
+    Error, cli,
+    mock::{MockResponse, MockTransport},
+};
+
+/// Runs the command line against a mock and returns the requests it sent.
+async fn run(config: &str, test: &str, args: &[&str]) -> Result<Vec<Value>, Error> {
+    // A config file of the test's own, so the user's config never leaks in.
+    let dir = TempDir::new(test);
+    let config = dir.write("config.toml", config);
+    let base = [
+        "--no-stdin",
+        "--output",
+        "ndjson",
+        "--config",
+        config.to_str().unwrap(),
+    ];
+    let cli = common::parse_cli(base.iter().chain(args).copied());
+    let transport = MockTransport::new().reply(MockResponse::text_stream(&["ok"]));
+    cli::run_with_transport(cli, transport.clone()).await?;
+    Ok(transport
+        .requests()
+        .iter()
+        .map(|r| serde_json::to_value(r).unwrap())
+        .collect())
+}
+
+const GATEWAY: &str = r#"
+[profiles.gateway]
+api_key_env = "SIMPLE_LLM_QUERY_TEST_UNSET_KEY"
+basic_auth = { username = "bob", password_env = "SIMPLE_LLM_QUERY_TEST_UNSET_PASSWORD" }
+"#;
+
+#[tokio::test]
+async fn credential_flags_replace_the_profile_credentials() {
+    for flag in [["--basic-auth", "alice:pw"], ["--api-key", "sk-test"]] {
+        let args = [flag[0], flag[1], "--profile", "gateway", "-p", "Hi"];
+        run(GATEWAY, "flags", &args).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn profile_credentials_need_their_variables() {
+    let err = run(GATEWAY, "profile", &["--profile", "gateway", "-p", "Hi"])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Config(_)), "{:?}", err);
+}
+
+#[tokio::test]
+async fn negative_seeds_are_accepted() {
+    let requests = run("", "seed", &["--seed", "-1", "-p", "Hi"])
+        .await
+        .unwrap();
+    assert_eq!(requests[0]["seed"], -1);
+}
+
+#[tokio::test]
+async fn sessions_do_not_need_profile_credentials() {
+    let config = format!("default_profile = \"gateway\"\n{}", GATEWAY);
+    let err = run(
+        &config,
+        "sessions",
+        &["sessions", "delete", "no-such-session"],
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("does not exist"), "{}", err);
+}
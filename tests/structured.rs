@@ -0,0 +1,39 @@
+mod common;
+
+use common::TempDir;
+use serde_json::json;
+use simple_llm_query::{Error, structured::StructuredOutput};
+
+fn schema_output(test: &str) -> StructuredOutput {
+    let dir = TempDir::new(test);
+    let schema = json!({
+        "type": "object",
+        "properties": { "name": { "type": "string" } },
+        "required": ["name"]
+    });
+    let path = dir.write("schema.json", &schema.to_string());
+    StructuredOutput::from_schema_file(&path, true).unwrap()
+}
+
+#[test]
+fn schemas_are_not_strict_by_default() {
+    let format = serde_json::to_value(schema_output("default").response_format()).unwrap();
+    assert_eq!(format["type"], "json_schema");
+    assert_eq!(format["json_schema"]["strict"], false);
+    assert_eq!(format["json_schema"]["schema"]["required"][0], "name");
+}
+
+#[test]
+fn strict_mode_is_opt_in() {
+    let output = schema_output("strict").strict(true);
+    let format = serde_json::to_value(output.response_format()).unwrap();
+    assert_eq!(format["json_schema"]["strict"], true);
+}
+
+#[test]
+fn answers_are_validated_against_the_schema() {
+    let output = schema_output("validate");
+    assert_eq!(output.parse(r#"{"name": "x"}"#).unwrap()["name"], "x");
+    let err = Error::from(output.parse(r#"{"name": 1}"#).unwrap_err());
+    assert!(matches!(err, Error::Structured(_)), "{:?}", err);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use common::TempDir;
+use serde_json::json;
+use simple_llm_query::{
+    Error,
+    tools::{self, CommandTool, Tool},
+};
+
+/// A temporary directory holding `inside/notes.txt` and `secret.txt` next to it.
+struct TempRoot {
+    dir: TempDir,
+}
+
+impl TempRoot {
+    fn new(test: &str) -> Self {
+        let dir = TempDir::new(test);
+        fs::create_dir_all(dir.path().join("inside/sub")).unwrap();
+        dir.write("inside/notes.txt", "hello");
+        dir.write("secret.txt", "top secret");
+        Self { dir }
+    }
+
+    fn inside(&self) -> PathBuf {
+        self.dir.path().join("inside")
+    }
+}
+
+fn tool(name: &str, root: &Path) -> Box<dyn Tool> {
+    tools::builtin(name, root).unwrap()
+}
+
+#[tokio::test]
+async fn builtin_tools_work_inside_the_root() {
+    let temp = TempRoot::new("inside");
+    let read = tool("read_file", &temp.inside());
+    let output = read.call(json!({ "path": "notes.txt" })).await.unwrap();
+    assert_eq!(output, "hello");
+
+    let list = tool("list_directory", &temp.inside());
+    let output = list.call(json!({ "path": "." })).await.unwrap();
+    assert_eq!(output, "notes.txt\nsub/");
+}
+
+#[tokio::test]
+async fn builtin_tools_refuse_paths_outside_the_root() {
+    let temp = TempRoot::new("outside");
+    let read = tool("read_file", &temp.inside());
+    let secret = temp.dir.path().join("secret.txt");
+    for path in [
+        "../secret.txt",
+        "sub/../../secret.txt",
+        secret.to_str().unwrap(),
+    ] {
+        let err = read.call(json!({ "path": path })).await.unwrap_err();
+        assert!(matches!(err, Error::Tool(_)), "{}: {:?}", path, err);
+    }
+
+    let list = tool("list_directory", &temp.inside());
+    let err = list.call(json!({ "path": ".." })).await.unwrap_err();
+    assert!(matches!(err, Error::Tool(_)), "{:?}", err);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlinks_out_of_the_root_are_refused() {
+    let temp = TempRoot::new("symlink");
+    std::os::unix::fs::symlink(
+        temp.dir.path().join("secret.txt"),
+        temp.inside().join("link"),
+    )
+    .unwrap();
+    let read = tool("read_file", &temp.inside());
+    let err = read.call(json!({ "path": "link" })).await.unwrap_err();
+    assert!(matches!(err, Error::Tool(_)), "{:?}", err);
+}
+
+fn command_tool(declaration: serde_json::Value) -> CommandTool {
+    serde_json::from_value(declaration).unwrap()
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn command_tools_get_their_arguments_on_stdin() {
+    let cat = command_tool(json!({ "name": "cat", "command": ["cat"] }));
+    let output = cat.call(json!({ "city": "Paris" })).await.unwrap();
+    assert_eq!(output, r#"{"city":"Paris"}"#);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn hung_command_tools_time_out() {
+    let sleep = command_tool(json!({
+        "name": "sleep",
+        "command": ["sleep", "30"],
+        "timeout_secs": 1
+    }));
+    let started = Instant::now();
+    let err = sleep.call(json!({})).await.unwrap_err();
+    assert!(matches!(err, Error::Tool(_)), "{:?}", err);
+    assert!(started.elapsed() < Duration::from_secs(10));
+}